@@ -0,0 +1,152 @@
+use std::{
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use model::{
+    distribution::Distribution,
+    histogram::{Axis, Histogram, Mode},
+    rng::Rng,
+};
+use output::Format;
+use parser::ast::Value;
+
+mod output;
+mod repl;
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use shoulda::Shoulda;
+
+    use super::Args;
+
+    #[test]
+    fn flags_after_the_expression() {
+        let args = Args::try_parse_from(["dice", "2d6", "+", "3", "--stats"]).unwrap();
+        args.expression.join(" ").should().eq("2d6 + 3".to_string());
+        args.stats.should().be_true();
+        let args = Args::try_parse_from(["dice", "--", "-d6", "--stats"]).unwrap();
+        args.expression
+            .join(" ")
+            .should()
+            .eq("-d6 --stats".to_string());
+        args.stats.should().be_false();
+        let args = Args::try_parse_from(["dice", "d20", "-1", "-r"]).unwrap();
+        args.expression.join(" ").should().eq("d20 -1".to_string());
+        args.roll.should().eq(Some(1));
+    }
+}
+
+/// Evaluates a dice expression and prints its distribution, starts an interactive session when no
+/// expression is given
+#[derive(Debug, Parser)]
+#[command(name = "dice", version)]
+struct Args {
+    /// the expression to evaluate, e.g. `2d6 + 3`, write `--` before an expression that starts
+    /// with a `-` like `dice -- -d6`
+    #[arg(num_args = 0.., allow_negative_numbers = true)]
+    expression: Vec<String>,
+
+    #[arg(short, long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// roll the expression this many times instead of printing its distribution
+    #[arg(short, long, num_args = 0..=1, default_missing_value = "1")]
+    roll: Option<usize>,
+
+    /// seed for --roll, the rolls are random when it isn't given
+    #[arg(long, requires = "roll")]
+    seed: Option<u64>,
+
+    /// only print the statistics of the distribution
+    #[arg(short, long, conflicts_with = "roll")]
+    stats: bool,
+
+    /// the length of the longest bar of the histogram
+    #[arg(long, default_value_t = 40)]
+    width: usize,
+
+    /// draw counts instead of probabilities in the histogram
+    #[arg(long)]
+    count: bool,
+
+    /// draw the chance of rolling at least each outcome in the histogram
+    #[arg(long)]
+    at_least: bool,
+
+    /// mark the chance of rolling at most each outcome in the histogram
+    #[arg(long)]
+    cumulative: bool,
+
+    /// draw the histogram with plain ascii characters
+    #[arg(long)]
+    ascii: bool,
+}
+
+fn eval(expression: &str) -> Result<Distribution, String> {
+    let value: Value = expression
+        .parse()
+        .map_err(|err: parser::ast::ValueParseError| {
+            let message = err.to_string();
+            let message = message.trim_end();
+            match err.span(expression) {
+                Some(span) => format!(
+                    "Parse Error: {message}\n{expression}\n{}{}",
+                    " ".repeat(expression[..span.start].chars().count()),
+                    "^".repeat(expression[span].chars().count().max(1)),
+                ),
+                None => format!("Parse Error: {message}"),
+            }
+        })?;
+    Ok(value.eval().map_err(|err| err.to_string())?.into())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if args.expression.is_empty() {
+        repl::run();
+        return ExitCode::SUCCESS;
+    }
+    let expression = args.expression.join(" ");
+    let dist = match eval(&expression) {
+        Ok(dist) => dist,
+        Err(err) => {
+            eprintln!("{}", err.trim_end());
+            return ExitCode::FAILURE;
+        }
+    };
+    let out = if let Some(times) = args.roll {
+        let seed = args.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|x| x.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        let mut rng = Rng::new(seed);
+        let rolls = (0..times).map(|_| dist.roll(&mut rng)).collect::<Vec<_>>();
+        output::rolls(&rolls, args.format)
+    } else if args.stats {
+        output::stats(&dist, args.format)
+    } else {
+        let histogram = Histogram {
+            width: args.width,
+            axis: if args.count {
+                Axis::Count
+            } else {
+                Axis::Probability
+            },
+            mode: if args.at_least {
+                Mode::AtLeast
+            } else {
+                Mode::Exact
+            },
+            cumulative: args.cumulative,
+            unicode: !args.ascii,
+        };
+        output::distribution(&dist, args.format, &histogram)
+    };
+    print!("{out}");
+    ExitCode::SUCCESS
+}
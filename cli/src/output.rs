@@ -0,0 +1,218 @@
+use clap::ValueEnum;
+use model::{distribution::Distribution, histogram::Histogram, svg::Chart, BigInt, Int};
+
+#[cfg(test)]
+mod tests {
+    use model::{dice::Dice, distribution::Distribution, posibility_space::PosibilitySpace};
+    use shoulda::Shoulda;
+
+    use super::{distribution, json, stats, table, Format};
+    use model::histogram::Histogram;
+
+    fn d4() -> Distribution {
+        PosibilitySpace::from(Dice(4)).into()
+    }
+
+    #[test]
+    fn table_output() {
+        table(&d4())
+            .should()
+            .eq("outcome count probability cumulative
+      1     1      25.00%     25.00%
+      2     1      25.00%     50.00%
+      3     1      25.00%     75.00%
+      4     1      25.00%    100.00%
+"
+            .to_string());
+    }
+
+    #[test]
+    fn csv_output() {
+        distribution(&d4(), Format::Csv, &Histogram::default())
+            .should()
+            .eq("outcome,count,probability,at_least,at_most
+1,1,0.25,1,0.25
+2,1,0.25,0.75,0.5
+3,1,0.25,0.5,0.75
+4,1,0.25,0.25,1
+"
+            .to_string());
+    }
+
+    #[test]
+    fn json_output() {
+        json(&d4()).should().eq(r#"{"outcomes":[{"outcome":1,"count":1,"probability":0.25,"cumulative":0.25},{"outcome":2,"count":1,"probability":0.25,"cumulative":0.5},{"outcome":3,"count":1,"probability":0.25,"cumulative":0.75},{"outcome":4,"count":1,"probability":0.25,"cumulative":1}],"stats":{"total":4,"mean":2.5,"variance":1.25,"standard_deviation":1.118033988749895,"min":1,"max":4}}
+"#
+        .to_string());
+    }
+
+    #[test]
+    fn stats_csv_output() {
+        stats(&d4(), Format::Csv)
+            .should()
+            .eq("total,mean,variance,standard_deviation,min,max
+4,2.5,1.25,1.118033988749895,1,4
+"
+            .to_string());
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+    Histogram,
+    Svg,
+}
+
+struct Row {
+    outcome: Int,
+    count: BigInt,
+    probability: f64,
+    cumulative: f64,
+}
+
+fn rows(dist: &Distribution) -> Vec<Row> {
+    dist.sorted()
+        .into_iter()
+        .zip(dist.probabilities())
+        .zip(dist.cumulative())
+        .map(
+            |(((outcome, count), (_, probability)), (_, cumulative))| Row {
+                outcome,
+                count,
+                probability,
+                cumulative,
+            },
+        )
+        .collect()
+}
+
+pub fn distribution(dist: &Distribution, format: Format, histogram: &Histogram) -> String {
+    match format {
+        Format::Table => table(dist),
+        Format::Csv => dist.to_csv(),
+        Format::Json => json(dist),
+        Format::Histogram => histogram.render(dist),
+        Format::Svg => {
+            let chart = Chart {
+                mode: histogram.mode,
+                ..Default::default()
+            };
+            chart.render(&[("", dist)]) + "\n"
+        }
+    }
+}
+
+fn table(dist: &Distribution) -> String {
+    let rows = rows(dist);
+    let header = ["outcome", "count", "probability", "cumulative"];
+    let cells = rows
+        .iter()
+        .map(|x| {
+            [
+                x.outcome.to_string(),
+                x.count.to_string(),
+                format!("{:.2}%", x.probability * 100.0),
+                format!("{:.2}%", x.cumulative * 100.0),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let widths = (0..header.len())
+        .map(|i| {
+            cells
+                .iter()
+                .map(|x| x[i].len())
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let mut out = header
+        .iter()
+        .zip(&widths)
+        .map(|(x, width)| format!("{x:>width$}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    out.push('\n');
+    for row in cells {
+        out += &row
+            .iter()
+            .zip(&widths)
+            .map(|(x, width)| format!("{x:>width$}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        out.push('\n');
+    }
+    out
+}
+
+fn json_stats(dist: &Distribution) -> String {
+    format!(
+        r#"{{"total":{},"mean":{},"variance":{},"standard_deviation":{},"min":{},"max":{}}}"#,
+        dist.total(),
+        dist.mean(),
+        dist.variance(),
+        dist.standard_deviation(),
+        dist.min().unwrap_or_default(),
+        dist.max().unwrap_or_default(),
+    )
+}
+
+fn json(dist: &Distribution) -> String {
+    let outcomes = rows(dist)
+        .into_iter()
+        .map(|x| {
+            format!(
+                r#"{{"outcome":{},"count":{},"probability":{},"cumulative":{}}}"#,
+                x.outcome, x.count, x.probability, x.cumulative
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        r#"{{"outcomes":[{outcomes}],"stats":{}}}"#,
+        json_stats(dist)
+    ) + "\n"
+}
+
+pub fn stats(dist: &Distribution, format: Format) -> String {
+    match format {
+        Format::Table | Format::Histogram | Format::Svg => format!(
+            "mean: {:.2}\nstandard deviation: {:.2}\nvariance: {:.2}\nrange: {}..={}\ntotal: {}\n",
+            dist.mean(),
+            dist.standard_deviation(),
+            dist.variance(),
+            dist.min().unwrap_or_default(),
+            dist.max().unwrap_or_default(),
+            dist.total(),
+        ),
+        Format::Csv => format!(
+            "total,mean,variance,standard_deviation,min,max\n{},{},{},{},{},{}\n",
+            dist.total(),
+            dist.mean(),
+            dist.variance(),
+            dist.standard_deviation(),
+            dist.min().unwrap_or_default(),
+            dist.max().unwrap_or_default(),
+        ),
+        Format::Json => json_stats(dist) + "\n",
+    }
+}
+
+pub fn rolls(rolls: &[Int], format: Format) -> String {
+    match format {
+        Format::Table | Format::Csv | Format::Histogram | Format::Svg => {
+            rolls.iter().map(|x| format!("{x}\n")).collect()
+        }
+        Format::Json => format!(
+            "[{}]\n",
+            rolls
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+};
+
+use model::{distribution::Distribution, histogram::Histogram};
+use parser::{
+    ast::Value,
+    eval::{Env, EvalValue},
+};
+
+use crate::output::{self, Format};
+
+#[cfg(test)]
+mod tests {
+    use shoulda::Shoulda;
+
+    use super::Session;
+
+    #[test]
+    fn assign_and_reference() {
+        let mut session = Session::default();
+        session.handle("atk = d20 + 7");
+        session
+            .handle("atk - 7")
+            .should()
+            .eq(Some("mean 10.50, sd 5.77, range 1..=20".to_string()));
+    }
+
+    #[test]
+    fn list() {
+        let mut session = Session::default();
+        session.handle("dmg = 2d6");
+        session.handle("atk = d20");
+        session.handle(":list").should().eq(Some(
+            "atk = d20 (mean 10.50, sd 5.77, range 1..=20)
+dmg = 2d6 (mean 7.00, sd 2.42, range 2..=12)"
+                .to_string(),
+        ));
+    }
+
+    #[test]
+    fn reassigning_keeps_the_old_value_in_the_expression() {
+        let mut session = Session::default();
+        session.handle("x = 1");
+        session.handle("x = x + 1");
+        session
+            .handle("x")
+            .should()
+            .eq(Some("mean 2.00, sd 0.00, range 2..=2".to_string()));
+    }
+
+    #[test]
+    fn history() {
+        let mut session = Session::default();
+        session.handle("d4");
+        session.handle("d6");
+        session
+            .handle(":history")
+            .should()
+            .eq(Some("1: d4\n2: d6".to_string()));
+        session
+            .handle("!1")
+            .should()
+            .eq(Some("d4\nmean 2.50, sd 1.12, range 1..=4".to_string()));
+    }
+
+    #[test]
+    fn binding_is_not_an_assignment() {
+        let mut session = Session::default();
+        session
+            .handle("roll x = d6; x * 2")
+            .should()
+            .eq(Some("mean 7.00, sd 3.42, range 2..=12".to_string()));
+        session.handle(":list").should().eq(Some("".to_string()));
+    }
+
+    #[test]
+    fn invalid_name() {
+        let mut session = Session::default();
+        session
+            .handle("d6 = 3")
+            .should()
+            .eq(Some("Invalid name: d6".to_string()));
+    }
+
+    #[test]
+    fn missing_expression() {
+        let mut session = Session::default();
+        session
+            .handle("bad = ")
+            .should()
+            .eq(Some("Missing expression for bad".to_string()));
+        session.handle(":list").should().eq(Some("".to_string()));
+    }
+
+    #[test]
+    fn success_counting_is_not_an_assignment() {
+        let mut session = Session::default();
+        let sixes = Some("mean 1.00, sd 0.91, range 0..=6".to_string());
+        session.handle("6d6[=6]").should().eq(sixes.clone());
+        session.handle("(6d6[=6])").should().eq(sixes);
+        session
+            .handle("5d10[8..=10]")
+            .should()
+            .eq(Some("mean 1.50, sd 1.02, range 0..=5".to_string()));
+        session.handle(":list").should().eq(Some("".to_string()));
+    }
+}
+
+enum Outcome {
+    Output(String),
+    Quit,
+}
+
+#[derive(Default)]
+struct Session {
+    env: Env,
+    /// the source of every stored result, ordered by name
+    names: BTreeMap<String, String>,
+    history: Vec<String>,
+}
+
+const HELP: &str = "name = expr    evaluate expr and store it as name
+expr           evaluate expr, stored names can be used in it
+:list          list every stored result
+:show name     draw the distribution of a stored result
+:history       list previous inputs, rerun one with !n
+:help          print this message
+:quit          exit";
+
+fn summary(dist: &Distribution) -> String {
+    format!(
+        "mean {:.2}, sd {:.2}, range {}..={}",
+        dist.mean(),
+        dist.standard_deviation(),
+        dist.min().unwrap_or_default(),
+        dist.max().unwrap_or_default(),
+    )
+}
+
+/// splits `name = expr` into its parts, the line has to have a single `=` outside of
+/// parentheses and brackets, so `==` and friends, `let` or `roll` bindings and the faces of a pool
+/// like `6d6[=6]` are left alone
+fn assignment(line: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    let mut split = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '=' if depth == 0 => {
+                let before = line[..i].ends_with(['=', '<', '>', '!', '.']);
+                let after = line[i + 1..].starts_with('=');
+                if before || after || split.is_some() {
+                    return None;
+                }
+                split = Some(i);
+            }
+            _ => {}
+        }
+    }
+    let split = split?;
+    let (name, expr) = (line[..split].trim(), line[split + 1..].trim());
+    let identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    identifier.then_some((name, expr))
+}
+
+impl Session {
+    fn eval(&self, expr: &str) -> Result<EvalValue, String> {
+        let value: Value = expr
+            .parse()
+            .map_err(|err: parser::ast::ValueParseError| format!("Parse Error: {err}"))?;
+        value.eval_with(&self.env).map_err(|err| err.to_string())
+    }
+
+    /// runs a single line of input, returning what to print or `None` when the session is over
+    fn handle(&mut self, line: &str) -> Option<String> {
+        match self.run(line) {
+            Outcome::Output(x) => Some(x.trim_end().to_string()),
+            Outcome::Quit => None,
+        }
+    }
+
+    fn run(&mut self, line: &str) -> Outcome {
+        let line = line.trim();
+        if let Some(n) = line.strip_prefix('!') {
+            return match n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| self.history.get(n.wrapping_sub(1)))
+            {
+                Some(previous) => {
+                    let previous = previous.clone();
+                    match self.run(&previous) {
+                        Outcome::Output(x) => Outcome::Output(format!("{previous}\n{x}")),
+                        Outcome::Quit => Outcome::Quit,
+                    }
+                }
+                None => Outcome::Output(format!("No history entry {n}")),
+            };
+        }
+        if let Some(command) = line.strip_prefix(':') {
+            let (command, arg) = command.split_once(' ').unwrap_or((command, ""));
+            return match command {
+                "q" | "quit" | "exit" => Outcome::Quit,
+                "h" | "help" => Outcome::Output(HELP.to_string()),
+                "history" => Outcome::Output(
+                    self.history
+                        .iter()
+                        .enumerate()
+                        .map(|(i, x)| format!("{}: {x}\n", i + 1))
+                        .collect(),
+                ),
+                "list" | "ls" => Outcome::Output(
+                    self.names
+                        .iter()
+                        .map(|(name, source)| {
+                            let dist: Distribution = self.env.get(name).unwrap().clone().into();
+                            format!("{name} = {source} ({})\n", summary(&dist))
+                        })
+                        .collect(),
+                ),
+                "show" | "inspect" => Outcome::Output(match self.env.get(arg.trim()) {
+                    Some(value) => {
+                        let dist: Distribution = value.clone().into();
+                        output::distribution(&dist, Format::Histogram, &Histogram::default())
+                            + &summary(&dist)
+                    }
+                    None => format!("Unknown name: {}", arg.trim()),
+                }),
+                _ => Outcome::Output(format!("Unknown command: {command}, try :help")),
+            };
+        }
+        if line.is_empty() {
+            return Outcome::Output(String::new());
+        }
+        self.history.push(line.to_string());
+        Outcome::Output(match assignment(line) {
+            Some((name, expr)) => match name.parse::<Value>() {
+                Ok(Value::Variable(_)) if expr.is_empty() => {
+                    format!("Missing expression for {name}")
+                }
+                Ok(Value::Variable(_)) => match self.eval(expr) {
+                    Ok(value) => {
+                        let out = summary(&value.clone().into());
+                        self.env.set(name, value);
+                        self.names.insert(name.to_string(), expr.to_string());
+                        format!("{name} = {out}")
+                    }
+                    Err(err) => err,
+                },
+                _ => format!("Invalid name: {name}"),
+            },
+            None => match self.eval(line) {
+                Ok(value) => summary(&value.into()),
+                Err(err) => err,
+            },
+        })
+    }
+}
+
+pub fn run() {
+    let mut session = Session::default();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!("Type :help for a list of commands");
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        match session.handle(&line) {
+            Some(x) if x.is_empty() => {}
+            Some(x) => println!("{x}"),
+            None => break,
+        }
+    }
+}
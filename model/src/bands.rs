@@ -0,0 +1,72 @@
+use shoulda::Shoulda;
+
+use crate::{distribution::Distribution, systems::Outcomes, BigInt, Int};
+
+/// Labelled ranges of outcomes, like the degrees of success of a check. Every band starts at its
+/// threshold and runs up to the next one, outcomes below the first threshold count towards the
+/// first band
+#[derive(Clone, Debug, Shoulda)]
+pub struct Bands(pub Vec<(Int, String)>);
+
+impl Bands {
+    /// a band starting at every threshold, ordered from the lowest
+    pub fn new<S: ToString>(bands: impl IntoIterator<Item = (Int, S)>) -> Self {
+        let mut bands = bands
+            .into_iter()
+            .map(|(from, label)| (from, label.to_string()))
+            .collect::<Vec<_>>();
+        bands.sort_by_key(|(from, _)| *from);
+        Self(bands)
+    }
+
+    /// the index of the band `x` falls in
+    pub fn band(&self, x: Int) -> usize {
+        self.0.iter().filter(|(from, _)| *from <= x).count().max(1) - 1
+    }
+
+    fn tally(&self, rolls: impl IntoIterator<Item = (usize, BigInt)>) -> Outcomes {
+        if self.0.is_empty() {
+            return Outcomes(Vec::new());
+        }
+        let labels = self.0.iter().map(|(_, x)| x.as_str()).collect::<Vec<_>>();
+        Outcomes::tally(&labels, rolls)
+    }
+
+    /// how many ways `dist` ends up in every band
+    pub fn categorize(&self, dist: &Distribution) -> Outcomes {
+        self.tally(dist.0.iter().map(|(x, amount)| (self.band(*x), *amount)))
+    }
+
+    /// like [`Bands::categorize`] for `die` plus `rest`, except that `die` showing `high` moves
+    /// the result one band up and showing `low` one band down, like a natural 20 or 1 in
+    /// Pathfinder 2e
+    pub fn categorize_natural(
+        &self,
+        die: &Distribution,
+        rest: &Distribution,
+        low: Int,
+        high: Int,
+    ) -> Outcomes {
+        let last = self.0.len().max(1) - 1;
+        self.tally(die.0.iter().flat_map(|(face, amount_face)| {
+            rest.0.iter().map(move |(x, amount_x)| {
+                let band = self.band(face.saturating_add(*x));
+                let band = if *face == high {
+                    (band + 1).min(last)
+                } else if *face == low {
+                    band.max(1) - 1
+                } else {
+                    band
+                };
+                (band, amount_face * amount_x)
+            })
+        }))
+    }
+}
+
+impl Distribution {
+    /// how many ways this ends up in every band of `bands`, see [`Bands`]
+    pub fn categorize(&self, bands: &Bands) -> Outcomes {
+        bands.categorize(self)
+    }
+}
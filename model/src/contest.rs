@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use shoulda::Shoulda;
+
+use crate::{distribution::Distribution, BigInt, Int};
+
+/// How an attacker rolling against a defender turns out, like `d20 + 3` opposed by `d20 + 5`.
+/// `win`, `tie` and `lose` are counted from the side of the attacker and add up to the total of
+/// `margin`
+#[derive(Clone, Debug, Shoulda)]
+pub struct Contest {
+    pub win: BigInt,
+    pub tie: BigInt,
+    pub lose: BigInt,
+    /// by how much the attacker beat the defender, negative where the defender won
+    pub margin: Distribution,
+}
+
+impl Contest {
+    /// `attacker` against `defender`, each rolled once and independently
+    pub fn new(attacker: &Distribution, defender: &Distribution) -> Self {
+        let margin = attacker.combine(defender, |a, d| a - d);
+        let count = |f: fn(Int) -> bool| {
+            margin
+                .0
+                .iter()
+                .filter(|(x, _)| f(**x))
+                .map(|(_, amount)| *amount)
+                .sum::<BigInt>()
+        };
+        Self {
+            win: count(|x| x > 0),
+            tie: count(|x| x == 0),
+            lose: count(|x| x < 0),
+            margin,
+        }
+    }
+
+    pub fn total(&self) -> BigInt {
+        self.win + self.tie + self.lose
+    }
+
+    pub fn win_probability(&self) -> f64 {
+        self.win as f64 / self.total() as f64
+    }
+
+    pub fn tie_probability(&self) -> f64 {
+        self.tie as f64 / self.total() as f64
+    }
+
+    pub fn lose_probability(&self) -> f64 {
+        self.lose as f64 / self.total() as f64
+    }
+
+    /// 1 where the attacker wins and 0 where the defender does, ties go to the defender
+    pub fn outcome(&self) -> Distribution {
+        Distribution(HashMap::from_iter(
+            [(1, self.win), (0, self.tie + self.lose)]
+                .into_iter()
+                .filter(|(_, amount)| *amount != 0),
+        ))
+    }
+}
+
+impl Distribution {
+    /// this distribution as the attacker against `defender`, see [`Contest`]
+    pub fn contest(&self, defender: &Self) -> Contest {
+        Contest::new(self, defender)
+    }
+}
@@ -1,10 +1,28 @@
-use crate::{Int, posibility_space::PosibilitySpace};
+use crate::{joint_distribution::JointDistribution, posibility_space::PosibilitySpace, Int};
 
 #[derive(Clone, Debug)]
 pub struct Dice(pub Int);
 
 impl From<Dice> for PosibilitySpace {
     fn from(val: Dice) -> Self {
-        PosibilitySpace((1..=val.0).into_iter().map(|x| (vec![x], 1)).collect())
+        PosibilitySpace((1..=val.0).map(|x| (vec![x], 1)).collect())
     }
-}
\ No newline at end of file
+}
+
+impl From<Dice> for JointDistribution {
+    fn from(val: Dice) -> Self {
+        val.symbols(|x| vec![x])
+    }
+}
+
+impl Dice {
+    /// this die with symbols on its faces instead of numbers, `symbols` gives how many of each
+    /// symbol a face shows, in the same order on every face
+    pub fn symbols<T: Fn(Int) -> Vec<Int>>(&self, symbols: T) -> JointDistribution {
+        let mut faces = JointDistribution(Default::default());
+        for face in 1..=self.0 {
+            *faces.0.entry(symbols(face)).or_insert(0) += 1;
+        }
+        faces
+    }
+}
@@ -1,16 +1,60 @@
 use std::{
     cmp,
     collections::HashMap,
+    hash::Hash,
     ops::{Add, Sub},
 };
 
 use shoulda::Shoulda;
 
-use crate::{posibility_space::PosibilitySpace, BigInt, Int};
+use crate::{posibility_space::PosibilitySpace, rng::Rng, BigInt, Int};
 
 #[derive(Clone, Debug, Shoulda)]
 pub struct Distribution(pub HashMap<Int, BigInt>);
 
+pub(crate) fn gcd(a: BigInt, b: BigInt) -> BigInt {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// the counts of every part scaled to a common total and by its weight, then added together and
+/// divided by what they have in common
+pub(crate) fn mix<'a, K: Hash + Eq + Clone + 'a>(
+    parts: impl IntoIterator<Item = (BigInt, &'a HashMap<K, BigInt>)>,
+) -> HashMap<K, BigInt> {
+    checked_mix(parts).expect("the counts of a mixture are too big")
+}
+
+/// like [`mix`], or `None` when the scaled counts don't fit in a [`BigInt`]
+pub(crate) fn checked_mix<'a, K: Hash + Eq + Clone + 'a>(
+    parts: impl IntoIterator<Item = (BigInt, &'a HashMap<K, BigInt>)>,
+) -> Option<HashMap<K, BigInt>> {
+    let parts = parts
+        .into_iter()
+        .filter(|(weight, counts)| *weight != 0 && !counts.is_empty())
+        .map(|(weight, counts)| (weight, counts, counts.values().sum::<BigInt>()))
+        .collect::<Vec<_>>();
+    let common = parts.iter().try_fold(1 as BigInt, |acc, (_, _, total)| {
+        (acc / gcd(acc, *total)).checked_mul(*total)
+    })?;
+    let mut new = HashMap::new();
+    for (weight, counts, total) in parts {
+        let scale = weight.checked_mul(common / total)?;
+        for (x, amount) in counts {
+            let entry = new.entry(x.clone()).or_insert(0 as BigInt);
+            *entry = entry.checked_add(amount.checked_mul(scale)?)?;
+        }
+    }
+    let divisor = new.values().fold(0, |acc, x| gcd(*x, acc));
+    if divisor > 1 {
+        new.values_mut().for_each(|x| *x /= divisor);
+    }
+    Some(new)
+}
+
 impl From<PosibilitySpace> for Distribution {
     fn from(val: PosibilitySpace) -> Self {
         let capacity = val.0.capacity();
@@ -71,7 +115,141 @@ impl Sub for Distribution {
 }
 
 impl Distribution {
+    /// applies `f` to every outcome, outcomes that end up the same are added together
     pub fn mutate<T: Fn(Int) -> Int>(self, f: T) -> Self {
-        Self(self.0.into_iter().map(|(l, r)| (f(l), r)).collect())
+        let mut new = HashMap::with_capacity(self.0.len());
+        for (x, amount) in self.0 {
+            *new.entry(f(x)).or_insert(0) += amount;
+        }
+        Self(new)
+    }
+
+    /// the distribution of `f` applied to an outcome of each, rolled independently
+    pub fn combine<T: Fn(Int, Int) -> Int>(&self, rhs: &Self, f: T) -> Self {
+        let mut new = HashMap::new();
+        for (x, amount_x) in self.0.iter() {
+            for (y, amount_y) in rhs.0.iter() {
+                *new.entry(f(*x, *y)).or_insert(0) += amount_x * amount_y;
+            }
+        }
+        Self(new)
+    }
+
+    /// picks one of `parts` with a chance proportional to its weight and rolls it, every part is
+    /// scaled to a common total first so the result stays exact, and the counts are kept as small
+    /// as they can be
+    pub fn mixture(parts: &[(BigInt, Distribution)]) -> Self {
+        Self(mix(parts.iter().map(|(weight, dist)| (*weight, &dist.0))))
+    }
+
+    /// only the outcomes where `f` holds, the chance of each is relative to the chance of `f`
+    /// holding at all
+    pub fn condition<T: Fn(Int) -> bool>(&self, f: T) -> Self {
+        let kept = self
+            .0
+            .iter()
+            .filter(|(x, _)| f(**x))
+            .map(|(x, amount)| (*x, *amount))
+            .collect();
+        Self(mix([(1, &kept)]))
+    }
+
+    /// like [`Distribution::mixture`] with the fraction `numerator / denominator` as the weight of
+    /// every part, the weights are scaled to a common denominator so the result stays exact, or
+    /// `None` when that doesn't fit in a [`BigInt`]
+    pub fn weighted_mixture(parts: &[((BigInt, BigInt), Distribution)]) -> Option<Self> {
+        let parts = parts.iter().filter(|((_, d), _)| *d != 0);
+        let common = parts.clone().try_fold(1 as BigInt, |acc, ((_, d), _)| {
+            (acc / gcd(acc, *d)).checked_mul(*d)
+        })?;
+        let weights = parts
+            .map(|((n, d), dist)| Some((n.checked_mul(common / d)?, &dist.0)))
+            .collect::<Option<Vec<_>>>()?;
+        checked_mix(weights).map(Self)
+    }
+
+    pub fn total(&self) -> BigInt {
+        self.0.values().sum()
+    }
+
+    /// every outcome with its amount, ordered by outcome
+    pub fn sorted(&self) -> Vec<(Int, BigInt)> {
+        let mut sorted = self.0.iter().map(|(x, y)| (*x, *y)).collect::<Vec<_>>();
+        sorted.sort_unstable_by_key(|(x, _)| *x);
+        sorted
+    }
+
+    pub fn probabilities(&self) -> Vec<(Int, f64)> {
+        let total = self.total() as f64;
+        self.sorted()
+            .into_iter()
+            .map(|(x, amount)| (x, amount as f64 / total))
+            .collect()
+    }
+
+    /// the probability of rolling each outcome or anything below it
+    pub fn cumulative(&self) -> Vec<(Int, f64)> {
+        let total = self.total() as f64;
+        self.sorted()
+            .into_iter()
+            .scan(0, |acc, (x, amount)| {
+                *acc += amount;
+                Some((x, *acc as f64 / total))
+            })
+            .collect()
+    }
+
+    /// the probability of rolling `n` or anything above it
+    pub fn probability_at_least(&self, n: Int) -> f64 {
+        let hits = self
+            .0
+            .iter()
+            .filter(|(x, _)| **x >= n)
+            .map(|(_, amount)| *amount)
+            .sum::<BigInt>();
+        hits as f64 / self.total() as f64
+    }
+
+    /// picks a single outcome, weighted by how many ways it can be rolled
+    pub fn roll(&self, rng: &mut Rng) -> Int {
+        let mut pick = rng.below(self.total());
+        for (x, amount) in self.sorted() {
+            if pick < amount {
+                return x;
+            }
+            pick -= amount;
+        }
+        unreachable!("picked outside of the distribution")
+    }
+
+    pub fn min(&self) -> Option<Int> {
+        self.0.keys().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Int> {
+        self.0.keys().max().copied()
+    }
+
+    pub fn mean(&self) -> f64 {
+        let total = self.total() as f64;
+        self.0
+            .iter()
+            .map(|(x, amount)| *x as f64 * *amount as f64)
+            .sum::<f64>()
+            / total
+    }
+
+    pub fn variance(&self) -> f64 {
+        let total = self.total() as f64;
+        let mean = self.mean();
+        self.0
+            .iter()
+            .map(|(x, amount)| (*x as f64 - mean).powi(2) * *amount as f64)
+            .sum::<f64>()
+            / total
+    }
+
+    pub fn standard_deviation(&self) -> f64 {
+        self.variance().sqrt()
     }
 }
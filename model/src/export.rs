@@ -0,0 +1,210 @@
+use std::{error::Error, fmt::Display};
+
+use shoulda::Shoulda;
+
+use crate::{
+    distribution::{gcd, Distribution},
+    BigInt, Int,
+};
+
+#[derive(Debug, Shoulda)]
+pub enum ImportError {
+    /// the line, counting from 1, that could not be read
+    Malformed(usize),
+    /// the probabilities are too imprecise to recover whole counts from
+    Imprecise,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Malformed(line) => write!(f, "Import Error: Malformed line {line}"),
+            ImportError::Imprecise => {
+                write!(
+                    f,
+                    "Import Error: Probabilities are too imprecise to recover counts"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+/// the simplest fraction within `tolerance` of `x`, found through its continued fraction
+fn rational(x: f64, tolerance: f64) -> Option<(BigInt, BigInt)> {
+    let (mut h0, mut h1): (BigInt, BigInt) = (0, 1);
+    let (mut k0, mut k1): (BigInt, BigInt) = (1, 0);
+    let mut r = x;
+    loop {
+        let a = r.floor();
+        if !(0.0..1e18).contains(&a) {
+            return None;
+        }
+        let a = a as BigInt;
+        let h = a.checked_mul(h1)?.checked_add(h0)?;
+        let k = a.checked_mul(k1)?.checked_add(k0)?;
+        if (x - h as f64 / k as f64).abs() <= tolerance || r == a as f64 {
+            return Some((h, k));
+        }
+        (h0, h1, k0, k1) = (h1, h, k1, k);
+        r = 1.0 / (r - a as f64);
+    }
+}
+
+/// turns probabilities back into whole counts over the smallest common total
+fn counts(probabilities: Vec<(Int, f64)>) -> Result<Distribution, ImportError> {
+    let fractions = probabilities
+        .into_iter()
+        .map(|(x, p)| {
+            rational(p, (p * 1e-10).max(f64::MIN_POSITIVE))
+                .map(|fraction| (x, fraction))
+                .ok_or(ImportError::Imprecise)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let total = fractions.iter().try_fold(1 as BigInt, |acc, (_, (_, k))| {
+        (acc / gcd(acc, *k))
+            .checked_mul(*k)
+            .filter(|x| *x < 1 << 96)
+            .ok_or(ImportError::Imprecise)
+    })?;
+    let dist = Distribution(
+        fractions
+            .into_iter()
+            .filter(|(_, (h, _))| *h != 0)
+            .map(|(x, (h, k))| (x, h * (total / k)))
+            .collect(),
+    );
+    if dist.total() != total {
+        return Err(ImportError::Imprecise);
+    }
+    Ok(dist)
+}
+
+/// the text of the quoted field at the start of `line`, with doubled quotes unescaped
+fn unquote(line: &str) -> String {
+    let mut name = String::new();
+    let mut chars = line.chars().skip(1).peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' && chars.next_if_eq(&'"').is_none() {
+            break;
+        }
+        name.push(c);
+    }
+    name
+}
+
+/// `x` as a decimal with 12 significant digits, without trailing zeros
+fn significant(x: f64) -> String {
+    let decimals = if x == 0.0 {
+        0
+    } else {
+        (11 - x.abs().log10().floor() as i32).max(0) as usize
+    };
+    let s = format!("{x:.decimals$}");
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    }
+}
+
+impl Distribution {
+    /// a csv table with a header and one row per outcome, ordered by outcome
+    pub fn to_csv(&self) -> String {
+        let total = self.total();
+        let mut out = "outcome,count,probability,at_least,at_most\n".to_string();
+        let mut below = 0;
+        for (x, amount) in self.sorted() {
+            let at_least = total - below;
+            below += amount;
+            out += &format!(
+                "{x},{amount},{},{},{}\n",
+                amount as f64 / total as f64,
+                at_least as f64 / total as f64,
+                below as f64 / total as f64
+            );
+        }
+        out
+    }
+
+    /// reads the outcome and count columns of a table written by [`Distribution::to_csv`]
+    pub fn from_csv(s: &str) -> Result<Self, ImportError> {
+        let mut lines = s.lines().enumerate().filter(|(_, x)| !x.trim().is_empty());
+        let header = lines
+            .next()
+            .map(|(_, x)| x.split(',').map(str::trim).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let outcome = header.iter().position(|x| *x == "outcome");
+        let count = header.iter().position(|x| *x == "count");
+        let (Some(outcome), Some(count)) = (outcome, count) else {
+            return Err(ImportError::Malformed(1));
+        };
+        lines
+            .map(|(i, line)| {
+                let cells = line.split(',').map(str::trim).collect::<Vec<_>>();
+                let x = cells.get(outcome).and_then(|x| x.parse().ok());
+                let amount = cells.get(count).and_then(|x| x.parse().ok());
+                x.zip(amount).ok_or(ImportError::Malformed(i + 1))
+            })
+            .collect::<Result<_, _>>()
+            .map(Distribution)
+    }
+
+    /// the layout anydice exports, a quoted name with the mean, standard deviation, minimum and
+    /// maximum, followed by the percentage of every outcome
+    pub fn to_anydice(&self, name: &str) -> String {
+        let mut out = format!(
+            "\"{}\",{},{},{},{}\n#,%\n",
+            name.replace('"', "\"\""),
+            significant(self.mean()),
+            significant(self.standard_deviation()),
+            self.min().unwrap_or_default(),
+            self.max().unwrap_or_default(),
+        );
+        for (x, p) in self.probabilities() {
+            out += &format!("{x},{}\n", significant(p * 100.0));
+        }
+        out
+    }
+
+    /// every named output of an anydice export, counts are recovered from the percentages by
+    /// finding the simplest fractions they were rounded from
+    pub fn from_anydice(s: &str) -> Result<Vec<(String, Self)>, ImportError> {
+        let mut outputs = Vec::new();
+        let mut current: Option<(String, Vec<(Int, f64)>)> = None;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            let cells = line
+                .split([',', '\t'])
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .collect::<Vec<_>>();
+            match cells.as_slice() {
+                [] => {}
+                [name, ..] if name.starts_with('"') => {
+                    outputs.extend(current.take());
+                    current = Some((unquote(line), Vec::new()));
+                }
+                ["#", "%"] => {
+                    if current.is_none() {
+                        current = Some((String::new(), Vec::new()));
+                    }
+                }
+                [x, p] => {
+                    let row = x.parse().ok().zip(p.parse::<f64>().ok());
+                    match (row, current.as_mut()) {
+                        (Some((x, p)), Some((_, rows))) => rows.push((x, p / 100.0)),
+                        _ => return Err(ImportError::Malformed(i + 1)),
+                    }
+                }
+                _ => return Err(ImportError::Malformed(i + 1)),
+            }
+        }
+        outputs.extend(current);
+        outputs
+            .into_iter()
+            .map(|(name, rows)| Ok((name, counts(rows)?)))
+            .collect()
+    }
+}
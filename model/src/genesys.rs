@@ -0,0 +1,153 @@
+use crate::{dice::Dice, joint_distribution::JointDistribution, Int};
+
+/// the symbols on the faces of the narrative dice of Genesys and Star Wars, in the order every
+/// face counts them in
+pub const SUCCESS: usize = 0;
+pub const FAILURE: usize = 1;
+pub const ADVANTAGE: usize = 2;
+pub const THREAT: usize = 3;
+pub const TRIUMPH: usize = 4;
+pub const DESPAIR: usize = 5;
+
+/// a die with the given symbols on each of its faces
+fn dice(faces: &[&[usize]]) -> JointDistribution {
+    Dice(faces.len() as Int).symbols(|face| {
+        let mut symbols = vec![0; 6];
+        faces[face as usize - 1]
+            .iter()
+            .for_each(|x| symbols[*x] += 1);
+        symbols
+    })
+}
+
+pub fn boost() -> JointDistribution {
+    dice(&[
+        &[],
+        &[],
+        &[SUCCESS],
+        &[SUCCESS, ADVANTAGE],
+        &[ADVANTAGE, ADVANTAGE],
+        &[ADVANTAGE],
+    ])
+}
+
+pub fn setback() -> JointDistribution {
+    dice(&[&[], &[], &[FAILURE], &[FAILURE], &[THREAT], &[THREAT]])
+}
+
+pub fn ability() -> JointDistribution {
+    dice(&[
+        &[],
+        &[SUCCESS],
+        &[SUCCESS],
+        &[SUCCESS, SUCCESS],
+        &[ADVANTAGE],
+        &[ADVANTAGE],
+        &[SUCCESS, ADVANTAGE],
+        &[ADVANTAGE, ADVANTAGE],
+    ])
+}
+
+pub fn difficulty() -> JointDistribution {
+    dice(&[
+        &[],
+        &[FAILURE],
+        &[FAILURE, FAILURE],
+        &[THREAT],
+        &[THREAT],
+        &[THREAT],
+        &[THREAT, THREAT],
+        &[FAILURE, THREAT],
+    ])
+}
+
+pub fn proficiency() -> JointDistribution {
+    dice(&[
+        &[],
+        &[SUCCESS],
+        &[SUCCESS],
+        &[SUCCESS, SUCCESS],
+        &[SUCCESS, SUCCESS],
+        &[ADVANTAGE],
+        &[SUCCESS, ADVANTAGE],
+        &[SUCCESS, ADVANTAGE],
+        &[SUCCESS, ADVANTAGE],
+        &[ADVANTAGE, ADVANTAGE],
+        &[ADVANTAGE, ADVANTAGE],
+        &[TRIUMPH],
+    ])
+}
+
+pub fn challenge() -> JointDistribution {
+    dice(&[
+        &[],
+        &[FAILURE],
+        &[FAILURE],
+        &[FAILURE, FAILURE],
+        &[FAILURE, FAILURE],
+        &[THREAT],
+        &[THREAT],
+        &[FAILURE, THREAT],
+        &[FAILURE, THREAT],
+        &[THREAT, THREAT],
+        &[THREAT, THREAT],
+        &[DESPAIR],
+    ])
+}
+
+/// How many of each die are rolled together, the positive dice of the character against the
+/// negative dice of the check
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pool {
+    pub boost: usize,
+    pub ability: usize,
+    pub proficiency: usize,
+    pub setback: usize,
+    pub difficulty: usize,
+    pub challenge: usize,
+}
+
+impl Pool {
+    /// how many of each symbol the dice show together, before any of them cancel
+    pub fn symbols(&self) -> JointDistribution {
+        let mut symbols = JointDistribution([(vec![0; 6], 1)].into());
+        for (amount, dice) in [
+            (self.boost, boost()),
+            (self.ability, ability()),
+            (self.proficiency, proficiency()),
+            (self.setback, setback()),
+            (self.difficulty, difficulty()),
+            (self.challenge, challenge()),
+        ] {
+            for _ in 0..amount {
+                symbols = symbols.sum(&dice);
+            }
+        }
+        symbols
+    }
+
+    /// what the roll comes to once the symbols cancel, see [`cancel`]
+    pub fn result(&self) -> JointDistribution {
+        cancel(&self.symbols())
+    }
+
+    /// the chance of the check succeeding, which takes more successes than failures
+    pub fn success_probability(&self) -> f64 {
+        self.result().component(0).probability_at_least(1)
+    }
+}
+
+/// The symbols of every outcome once they cancel, a triumph is also a success and a despair also a
+/// failure, then failures cancel successes and threats cancel advantages. Every outcome becomes
+/// the net successes, the net advantages, the triumphs and the despairs, where negative net
+/// successes are failures and negative net advantages are threats
+pub fn cancel(symbols: &JointDistribution) -> JointDistribution {
+    symbols.mutate(|x| {
+        vec![
+            x[SUCCESS] + x[TRIUMPH] - x[FAILURE] - x[DESPAIR],
+            x[ADVANTAGE] - x[THREAT],
+            x[TRIUMPH],
+            x[DESPAIR],
+        ]
+    })
+}
@@ -0,0 +1,119 @@
+use crate::{distribution::Distribution, BigInt};
+
+/// What the length of a bar stands for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Axis {
+    #[default]
+    Probability,
+    Count,
+}
+
+/// Which value is drawn for each outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// rolling exactly the outcome
+    #[default]
+    Exact,
+    /// rolling the outcome or anything above it
+    AtLeast,
+    /// rolling the outcome or anything below it
+    AtMost,
+}
+
+/// Renders a [`Distribution`] as a horizontal bar chart in plain text, one line per outcome
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    /// the length of the longest bar, in characters
+    pub width: usize,
+    pub axis: Axis,
+    pub mode: Mode,
+    /// marks the chance of rolling at most each outcome on every bar, where the full width is 100%
+    pub cumulative: bool,
+    /// draws with block characters that allow eighths of a character, plain `#` otherwise
+    pub unicode: bool,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            width: 40,
+            axis: Axis::default(),
+            mode: Mode::default(),
+            cumulative: false,
+            unicode: true,
+        }
+    }
+}
+
+const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+impl Histogram {
+    fn bar(&self, value: BigInt, max: BigInt) -> Vec<char> {
+        let eighths = if max == 0 {
+            0
+        } else {
+            (value as f64 / max as f64 * self.width as f64 * 8.0).round() as usize
+        };
+        let mut bar = if self.unicode {
+            let mut bar = vec!['█'; eighths / 8];
+            if eighths % 8 != 0 {
+                bar.push(EIGHTHS[eighths % 8]);
+            }
+            bar
+        } else {
+            vec!['#'; (eighths + 4) / 8]
+        };
+        bar.resize(self.width, ' ');
+        bar
+    }
+
+    fn label(&self, value: BigInt, total: BigInt) -> String {
+        match self.axis {
+            Axis::Probability => format!("{:.2}%", value as f64 / total as f64 * 100.0),
+            Axis::Count => value.to_string(),
+        }
+    }
+
+    pub fn render(&self, dist: &Distribution) -> String {
+        let sorted = dist.sorted();
+        let total = dist.total();
+        let rows = sorted
+            .iter()
+            .scan((0, 0), |(below, at_most), (x, amount)| {
+                *below = *at_most;
+                *at_most += amount;
+                let value = match self.mode {
+                    Mode::Exact => *amount,
+                    Mode::AtLeast => total - *below,
+                    Mode::AtMost => *at_most,
+                };
+                Some((*x, value, *at_most))
+            })
+            .collect::<Vec<_>>();
+        let max = rows.iter().map(|(_, x, _)| *x).max().unwrap_or_default();
+        let outcome_width = rows
+            .iter()
+            .map(|(x, _, _)| x.to_string().len())
+            .max()
+            .unwrap_or_default();
+        let (edge, marker) = if self.unicode {
+            ('│', '┼')
+        } else {
+            ('|', '+')
+        };
+        rows.into_iter()
+            .map(|(x, value, at_most)| {
+                let mut bar = self.bar(value, max);
+                if self.cumulative && self.width > 0 {
+                    let at = (at_most as f64 / total as f64 * self.width as f64).round() as usize;
+                    bar[at.clamp(1, self.width) - 1] = marker;
+                }
+                format!(
+                    "{x:>outcome_width$} {edge}{}{edge} {}\n",
+                    bar.into_iter().collect::<String>(),
+                    self.label(value, total)
+                )
+            })
+            .collect()
+    }
+}
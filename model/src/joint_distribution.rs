@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use shoulda::Shoulda;
+
+use crate::{
+    distribution::{mix, Distribution},
+    BigInt, Int,
+};
+
+/// How often every combination of several outcomes happens, like the number of hits together with
+/// the damage they do. Every key has the same length, one outcome for each component
+#[derive(Clone, Debug, Shoulda)]
+pub struct JointDistribution(pub HashMap<Vec<Int>, BigInt>);
+
+impl From<Distribution> for JointDistribution {
+    fn from(val: Distribution) -> Self {
+        Self(
+            val.0
+                .into_iter()
+                .map(|(x, amount)| (vec![x], amount))
+                .collect(),
+        )
+    }
+}
+
+impl JointDistribution {
+    pub fn total(&self) -> BigInt {
+        self.0.values().sum()
+    }
+
+    /// every outcome with its amount, ordered by outcome
+    pub fn sorted(&self) -> Vec<(Vec<Int>, BigInt)> {
+        let mut sorted = self
+            .0
+            .iter()
+            .map(|(x, y)| (x.clone(), *y))
+            .collect::<Vec<_>>();
+        sorted.sort_unstable();
+        sorted
+    }
+
+    /// the outcomes of both rolled independently, with the components of `rhs` after those of
+    /// `self`
+    pub fn product(&self, rhs: &Self) -> Self {
+        let mut new = HashMap::with_capacity(self.0.len() * rhs.0.len());
+        for (x, amount_x) in self.0.iter() {
+            for (y, amount_y) in rhs.0.iter() {
+                let key = x.iter().chain(y).copied().collect();
+                *new.entry(key).or_insert(0) += amount_x * amount_y;
+            }
+        }
+        Self(new)
+    }
+
+    /// the outcomes of both rolled independently and added component by component, like a pool
+    /// of dice that each show several symbols
+    pub fn sum(&self, rhs: &Self) -> Self {
+        let mut new = HashMap::new();
+        for (x, amount_x) in self.0.iter() {
+            for (y, amount_y) in rhs.0.iter() {
+                let key = x.iter().zip(y).map(|(x, y)| x + y).collect();
+                *new.entry(key).or_insert(0) += amount_x * amount_y;
+            }
+        }
+        Self(new)
+    }
+
+    /// applies `f` to every outcome, outcomes that end up the same are added together
+    pub fn mutate<T: Fn(&[Int]) -> Vec<Int>>(&self, f: T) -> Self {
+        let mut new = HashMap::new();
+        for (x, amount) in self.0.iter() {
+            *new.entry(f(x)).or_insert(0) += amount;
+        }
+        Self(new)
+    }
+
+    /// picks one of `parts` with a chance proportional to its weight, like
+    /// [`Distribution::mixture`]
+    pub fn mixture(parts: &[(BigInt, JointDistribution)]) -> Self {
+        Self(mix(parts.iter().map(|(weight, dist)| (*weight, &dist.0))))
+    }
+
+    /// only the components at `indices`, in that order, the others are summed over
+    pub fn marginalize(&self, indices: &[usize]) -> Self {
+        let mut new = HashMap::new();
+        for (x, amount) in self.0.iter() {
+            let key = indices.iter().map(|i| x[*i]).collect();
+            *new.entry(key).or_insert(0) += amount;
+        }
+        Self(new)
+    }
+
+    /// only the outcomes where `f` holds, the chance of each is relative to the chance of `f`
+    /// holding at all
+    pub fn condition<T: Fn(&[Int]) -> bool>(&self, f: T) -> Self {
+        let kept = self
+            .0
+            .iter()
+            .filter(|(x, _)| f(x))
+            .map(|(x, amount)| (x.clone(), *amount))
+            .collect();
+        Self(mix([(1, &kept)]))
+    }
+
+    /// the distribution of `f` applied to every outcome
+    pub fn project<T: Fn(&[Int]) -> Int>(&self, f: T) -> Distribution {
+        let mut new = HashMap::new();
+        for (x, amount) in self.0.iter() {
+            *new.entry(f(x)).or_insert(0) += amount;
+        }
+        Distribution(new)
+    }
+
+    /// the distribution of the component at `index` on its own
+    pub fn component(&self, index: usize) -> Distribution {
+        self.project(|x| x[index])
+    }
+}
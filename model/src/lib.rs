@@ -1,6 +1,21 @@
+pub mod bands;
+pub mod contest;
 pub mod dice;
 pub mod distribution;
+pub mod export;
+pub mod genesys;
+pub mod histogram;
+pub mod joint_distribution;
+pub mod pool;
 pub mod posibility_space;
+pub mod rng;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod svg;
+pub mod systems;
+
+pub type Int = i32;
+pub type BigInt = u128;
 
 #[cfg(test)]
 mod tests {
@@ -10,6 +25,11 @@ mod tests {
     use crate::distribution::Distribution;
     use crate::posibility_space::PosibilitySpace;
 
+    /// `n` dice with `sides` sides each
+    fn dice(n: u128, sides: i32) -> PosibilitySpace {
+        PosibilitySpace::from(Dice(sides)).multiply(n)
+    }
+
     #[test]
     fn destribution_of_5d20kh3() {
         let d20: PosibilitySpace = Dice(20).into();
@@ -198,7 +218,798 @@ mod tests {
             .into(),
         ));
     }
-}
 
-pub type Int = i32;
-pub type BigInt = u64;
+    #[cfg(test)]
+    mod stats {
+        use shoulda::Shoulda;
+
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::posibility_space::PosibilitySpace;
+        use crate::rng::Rng;
+
+        fn two_d6() -> Distribution {
+            PosibilitySpace::from(Dice(6)).multiply(2).into()
+        }
+
+        #[test]
+        fn total() {
+            two_d6().total().should().eq(36);
+        }
+
+        #[test]
+        fn sorted() {
+            let sorted = Distribution::from(PosibilitySpace::from(Dice(4))).sorted();
+            sorted.should().eq(vec![(1, 1), (2, 1), (3, 1), (4, 1)]);
+        }
+
+        #[test]
+        fn mutate_merges_outcomes() {
+            two_d6()
+                .mutate(|x| x / 4)
+                .sorted()
+                .should()
+                .eq(vec![(0, 3), (1, 18), (2, 14), (3, 1)]);
+        }
+
+        #[test]
+        fn mixture() {
+            let d4 = Distribution::from(PosibilitySpace::from(Dice(4)));
+            let d6 = Distribution::from(PosibilitySpace::from(Dice(6)));
+            let mixed = Distribution::mixture(&[(1, d4), (1, d6)]);
+            mixed
+                .sorted()
+                .should()
+                .eq(vec![(1, 5), (2, 5), (3, 5), (4, 5), (5, 2), (6, 2)]);
+            mixed.probability_at_least(5).should().eq(4.0 / 24.0);
+        }
+
+        #[test]
+        fn condition() {
+            two_d6()
+                .condition(|x| x >= 10)
+                .sorted()
+                .should()
+                .eq(vec![(10, 3), (11, 2), (12, 1)]);
+            two_d6()
+                .condition(|x| x % 2 == 0)
+                .sorted()
+                .should()
+                .eq(vec![(2, 1), (4, 3), (6, 5), (8, 5), (10, 3), (12, 1)]);
+        }
+
+        #[test]
+        fn weighted_mixture() {
+            let d8 = Distribution::from(PosibilitySpace::from(Dice(8)));
+            let mixed =
+                Distribution::weighted_mixture(&[((3, 10), d8), ((7, 10), two_d6())]).unwrap();
+            mixed.total().should().eq(720);
+            mixed.0[&1].should().eq(27);
+            mixed.0[&7].should().eq(27 + 6 * 14);
+            let thirds = Distribution::weighted_mixture(&[
+                ((1, 3), Distribution([(0, 1)].into())),
+                ((1, 2), Distribution([(1, 1)].into())),
+            ])
+            .unwrap();
+            thirds.sorted().should().eq(vec![(0, 2), (1, 3)]);
+            let primes = [4294967291, 4294967279, 4294967231, 4294967197, 4294967189];
+            let parts = primes
+                .iter()
+                .enumerate()
+                .map(|(i, d)| ((1, *d), Distribution([(i as crate::Int, 1)].into())))
+                .collect::<Vec<_>>();
+            Distribution::weighted_mixture(&parts)
+                .is_none()
+                .should()
+                .be_true();
+        }
+
+        #[test]
+        fn combine() {
+            let d4 = Distribution::from(PosibilitySpace::from(Dice(4)));
+            d4.combine(&d4, std::cmp::max).sorted().should().eq(vec![
+                (1, 1),
+                (2, 3),
+                (3, 5),
+                (4, 7),
+            ]);
+        }
+
+        #[test]
+        fn cumulative() {
+            let cumulative = Distribution::from(PosibilitySpace::from(Dice(4))).cumulative();
+            cumulative
+                .should()
+                .eq(vec![(1, 0.25), (2, 0.5), (3, 0.75), (4, 1.0)]);
+        }
+
+        #[test]
+        fn mean_and_variance() {
+            let dist = two_d6();
+            dist.mean().should().eq(7.0);
+            dist.variance().should().eq(35.0 / 6.0);
+            dist.min().should().eq(Some(2));
+            dist.max().should().eq(Some(12));
+        }
+
+        #[test]
+        fn probability_at_least() {
+            two_d6().probability_at_least(10).should().eq(6.0 / 36.0);
+            two_d6().probability_at_least(2).should().eq(1.0);
+        }
+
+        #[test]
+        fn roll_is_reproducible() {
+            let dist = two_d6();
+            let rolls = |seed| {
+                let mut rng = Rng::new(seed);
+                (0..20).map(|_| dist.roll(&mut rng)).collect::<Vec<_>>()
+            };
+            rolls(7).should().eq(rolls(7));
+            rolls(7)
+                .iter()
+                .all(|x| (2..=12).contains(x))
+                .should()
+                .be_true();
+        }
+
+        #[test]
+        fn total_beyond_64_bits() {
+            let d6: Distribution = PosibilitySpace::from(Dice(6)).into();
+            let dist = (0..30).fold(Distribution([(0, 1)].into()), |acc, _| acc + d6.clone());
+            dist.total().should().eq(6u128.pow(30));
+        }
+    }
+
+    #[cfg(test)]
+    mod histogram {
+        use shoulda::Shoulda;
+
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::histogram::{Axis, Histogram, Mode};
+        use crate::posibility_space::PosibilitySpace;
+
+        fn two_d3() -> Distribution {
+            PosibilitySpace::from(Dice(3)).multiply(2).into()
+        }
+
+        #[test]
+        fn ascii() {
+            let histogram = Histogram {
+                width: 6,
+                unicode: false,
+                ..Default::default()
+            };
+            histogram.render(&two_d3()).should().eq("2 |##    | 11.11%
+3 |####  | 22.22%
+4 |######| 33.33%
+5 |####  | 22.22%
+6 |##    | 11.11%
+"
+            .to_string());
+        }
+
+        #[test]
+        fn unicode_eighths() {
+            let histogram = Histogram {
+                width: 2,
+                axis: Axis::Count,
+                ..Default::default()
+            };
+            histogram.render(&two_d3()).should().eq("2 │▋ │ 1
+3 │█▍│ 2
+4 │██│ 3
+5 │█▍│ 2
+6 │▋ │ 1
+"
+            .to_string());
+        }
+
+        #[test]
+        fn at_least_with_cumulative() {
+            let histogram = Histogram {
+                width: 9,
+                mode: Mode::AtLeast,
+                axis: Axis::Count,
+                cumulative: true,
+                unicode: false,
+            };
+            histogram.render(&two_d3()).should().eq("2 |+########| 9
+3 |##+##### | 8
+4 |#####+   | 6
+5 |###    + | 3
+6 |#       +| 1
+"
+            .to_string());
+        }
+    }
+
+    #[cfg(test)]
+    mod svg {
+        use shoulda::Shoulda;
+
+        use super::dice;
+        use crate::distribution::Distribution;
+        use crate::histogram::Mode;
+        use crate::svg::{Chart, Style};
+
+        #[test]
+        fn bar_chart() {
+            let svg = Chart::default().render(&[("d4", &dice(1, 4).into())]);
+            svg.starts_with("<svg").should().be_true();
+            svg.ends_with("</svg>").should().be_true();
+            svg.matches(r#"class="bar""#).count().should().eq(4);
+            svg.contains(r#"class="legend""#).should().be_false();
+        }
+
+        #[test]
+        fn overlaid_lines_with_legend() {
+            let chart = Chart {
+                style: Style::Line,
+                ..Default::default()
+            };
+            let svg = chart.render(&[
+                ("2d6 & more", &dice(2, 6).into()),
+                ("<d12>", &dice(1, 12).into()),
+            ]);
+            svg.matches(r#"class="line""#).count().should().eq(2);
+            svg.matches(r#"class="legend""#).count().should().eq(2);
+            svg.contains("2d6 &amp; more").should().be_true();
+            svg.contains("&lt;d12&gt;").should().be_true();
+        }
+
+        #[test]
+        fn cumulative_reaches_the_top() {
+            let chart = Chart {
+                style: Style::Line,
+                mode: Mode::AtMost,
+                ..Default::default()
+            };
+            let svg = chart.render(&[("d4", &dice(1, 4).into())]);
+            svg.contains(r#",40.00" fill="none""#).should().be_true();
+            svg.contains(">100%</text>").should().be_true();
+        }
+
+        #[test]
+        fn only_outcomes_that_can_be_rolled() {
+            let sparse = Distribution::from(dice(1, 6)).mutate(|x| x * 100000);
+            let svg = Chart::default().render(&[("d6 * 100000", &sparse)]);
+            svg.matches(r#"class="bar""#).count().should().eq(6);
+            svg.contains(">600000</text>").should().be_true();
+            let svg =
+                Chart::default().render(&[("d4", &dice(1, 4).into()), ("d8", &dice(1, 8).into())]);
+            svg.matches(r#"class="bar""#).count().should().eq(4 + 8);
+        }
+    }
+
+    #[cfg(test)]
+    mod joint {
+        use shoulda::Shoulda;
+
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::joint_distribution::JointDistribution;
+        use crate::posibility_space::PosibilitySpace;
+
+        fn dice(sides: i32) -> JointDistribution {
+            Distribution::from(PosibilitySpace::from(Dice(sides))).into()
+        }
+
+        #[test]
+        fn product_and_marginalize() {
+            let joint = dice(4).product(&dice(6));
+            joint.total().should().eq(24);
+            joint.0[&vec![4, 6]].should().eq(1);
+            joint.marginalize(&[1]).sorted().should().eq(vec![
+                (vec![1], 4),
+                (vec![2], 4),
+                (vec![3], 4),
+                (vec![4], 4),
+                (vec![5], 4),
+                (vec![6], 4),
+            ]);
+            joint
+                .marginalize(&[1, 0])
+                .0
+                .get(&vec![6, 4])
+                .should()
+                .eq(Some(&1));
+        }
+
+        #[test]
+        fn project() {
+            let two_d4 = Distribution::from(PosibilitySpace::from(Dice(4)).multiply(2));
+            dice(4)
+                .product(&dice(4))
+                .project(|x| x[0] + x[1])
+                .should()
+                .eq(two_d4);
+        }
+
+        #[test]
+        fn condition() {
+            let joint = dice(4).product(&dice(4));
+            let sum_at_least_7 = joint.condition(|x| x[0] + x[1] >= 7);
+            sum_at_least_7.total().should().eq(3);
+            sum_at_least_7
+                .component(0)
+                .sorted()
+                .should()
+                .eq(vec![(3, 1), (4, 2)]);
+        }
+
+        #[test]
+        fn mixture() {
+            let mixed = JointDistribution::mixture(&[
+                (1, dice(2).product(&dice(1))),
+                (3, dice(1).product(&dice(1)).project(|_| 5).into()),
+            ]);
+            mixed
+                .sorted()
+                .should()
+                .eq(vec![(vec![1, 1], 1), (vec![2, 1], 1), (vec![5], 6)]);
+        }
+    }
+
+    #[cfg(test)]
+    mod contest {
+        use shoulda::Shoulda;
+
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::posibility_space::PosibilitySpace;
+
+        fn d20_plus(bonus: i32) -> Distribution {
+            Distribution::from(PosibilitySpace::from(Dice(20))).mutate(|x| x + bonus)
+        }
+
+        #[test]
+        fn win_tie_lose() {
+            let contest = d20_plus(3).contest(&d20_plus(5));
+            contest.total().should().eq(400);
+            contest.win.should().eq(153);
+            contest.tie.should().eq(18);
+            contest.lose.should().eq(229);
+            (contest.win_probability() + contest.tie_probability() + contest.lose_probability())
+                .should()
+                .eq(1.0);
+        }
+
+        #[test]
+        fn margin() {
+            let contest = d20_plus(0).contest(&d20_plus(0));
+            contest.margin.min().should().eq(Some(-19));
+            contest.margin.max().should().eq(Some(19));
+            contest.margin.0[&0].should().eq(20);
+            contest.margin.0[&-19].should().eq(1);
+        }
+
+        #[test]
+        fn ties_go_to_the_defender() {
+            let d2 = Distribution::from(PosibilitySpace::from(Dice(2)));
+            d2.contest(&d2)
+                .outcome()
+                .should()
+                .eq(Distribution([(0, 3), (1, 1)].into()));
+        }
+    }
+
+    #[cfg(test)]
+    mod pool {
+        use shoulda::Shoulda;
+
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::pool::Scoring;
+        use crate::posibility_space::PosibilitySpace;
+
+        /// old world of darkness, 7 and up succeeds and a 1 takes a success away
+        fn world_of_darkness() -> Scoring {
+            Scoring(
+                (1..=10)
+                    .map(|x| (x, (x >= 7) as i32 - (x == 1) as i32))
+                    .collect(),
+            )
+        }
+
+        #[test]
+        fn botch() {
+            let d10 = Distribution::from(PosibilitySpace::from(Dice(10)));
+            let score = d10.score_pool(2, &world_of_darkness());
+            score.total().should().eq(100);
+            // a 1 and a 2 to 6 either way around, or two 1s
+            score.botch.should().eq(2 * 5 + 1);
+            score.botch_probability().should().eq(0.11);
+            score.net.0[&-2].should().eq(1);
+            score.net.0[&0].should().eq(25 + 2 * 4);
+            score.net.0[&2].should().eq(16);
+        }
+
+        #[test]
+        fn same_as_every_roll() {
+            let scoring = Scoring([(1, -1), (5, 1), (6, 2)].into());
+            let d6 = Distribution::from(PosibilitySpace::from(Dice(6)));
+            let pool = PosibilitySpace::from(Dice(6)).multiply(4).score(&scoring);
+            let score = d6.score_pool(4, &scoring);
+            score.net.should().eq(pool.net);
+            score.botch.should().eq(pool.botch);
+            // only 1 to 4 but not only 2 to 4
+            score.botch.should().eq(4u128.pow(4) - 3u128.pow(4));
+        }
+    }
+
+    #[cfg(test)]
+    mod genesys {
+        use shoulda::Shoulda;
+
+        use super::dice;
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::genesys::{self, Pool, ADVANTAGE, SUCCESS, TRIUMPH};
+        use crate::joint_distribution::JointDistribution;
+
+        #[test]
+        fn symbol_dice() {
+            let proficiency = genesys::proficiency();
+            proficiency.total().should().eq(12);
+            proficiency
+                .component(SUCCESS)
+                .sorted()
+                .should()
+                .eq(vec![(0, 5), (1, 5), (2, 2)]);
+            proficiency.component(TRIUMPH).0[&1].should().eq(1);
+            Dice(3)
+                .symbols(|x| if x < 3 { vec![1, 0] } else { vec![0, 2] })
+                .sorted()
+                .should()
+                .eq(vec![(vec![0, 2], 1), (vec![1, 0], 2)]);
+            JointDistribution::from(Dice(4))
+                .component(0)
+                .should()
+                .eq(Distribution::from(dice(1, 4)));
+        }
+
+        #[test]
+        fn sum() {
+            let pool = Pool {
+                ability: 2,
+                ..Default::default()
+            };
+            let symbols = pool.symbols();
+            symbols.total().should().eq(64);
+            // both dice show two advantages, or one shows both and the other one
+            symbols
+                .condition(|x| x[SUCCESS] == 0 && x[ADVANTAGE] == 3)
+                .total()
+                .should()
+                .eq(1);
+            symbols.component(SUCCESS).0[&4].should().eq(1);
+        }
+
+        #[test]
+        fn cancel() {
+            let pool = Pool {
+                ability: 1,
+                difficulty: 1,
+                ..Default::default()
+            };
+            // a success against no failures, or two successes against at most one
+            pool.success_probability().should().eq(22.0 / 64.0);
+            let result = pool.result();
+            result.total().should().eq(64);
+            // a blank against a threat, an advantage against two threats or a success against a
+            // failure and a threat
+            result.0[&vec![0, -1, 0, 0]].should().eq(3 + 2 + 2);
+        }
+
+        #[test]
+        fn triumph_is_a_success() {
+            let pool = Pool {
+                proficiency: 1,
+                challenge: 1,
+                ..Default::default()
+            };
+            let result = pool.result();
+            // the triumph against a blank
+            result.0[&vec![1, 0, 1, 0]].should().eq(1);
+            // the triumph against the despair
+            result.0[&vec![0, 0, 1, 1]].should().eq(1);
+        }
+    }
+
+    #[cfg(test)]
+    mod systems {
+        use shoulda::Shoulda;
+
+        use crate::{
+            systems::{self, Advantage},
+            Int,
+        };
+
+        #[test]
+        fn dnd5e_attack() {
+            let attack = systems::dnd5e_attack(5, 15, Advantage::Normal);
+            attack.total().should().eq(20);
+            attack.get("miss").should().eq(9);
+            attack.get("hit").should().eq(10);
+            attack.get("critical hit").should().eq(1);
+            // a 1 misses even when the total would hit
+            systems::dnd5e_attack(20, 5, Advantage::Normal)
+                .get("miss")
+                .should()
+                .eq(1);
+            let advantage = systems::dnd5e_attack(5, 15, Advantage::Advantage);
+            advantage
+                .probability("critical hit")
+                .should()
+                .eq(39.0 / 400.0);
+            let disadvantage = systems::dnd5e_attack(5, 15, Advantage::Disadvantage);
+            disadvantage.get("critical hit").should().eq(1);
+        }
+
+        #[test]
+        fn pf2e_check() {
+            systems::pf2e_check(10, 20).0.should().eq(vec![
+                ("critical failure".to_string(), 1),
+                ("failure".to_string(), 8),
+                ("success".to_string(), 10),
+                ("critical success".to_string(), 1),
+            ]);
+            // a 20 that still misses by 10 is only a failure
+            let hopeless = systems::pf2e_check(0, 31);
+            hopeless.get("failure").should().eq(1);
+            hopeless.get("critical failure").should().eq(19);
+            systems::pf2e_check(0, Int::MAX)
+                .get("critical failure")
+                .should()
+                .eq(19);
+            // only a 1 keeps it from always being a critical success
+            systems::pf2e_check(Int::MAX, Int::MIN)
+                .get("critical success")
+                .should()
+                .eq(19);
+        }
+
+        #[test]
+        fn shadowrun_test() {
+            systems::shadowrun_test(1, 1).0.should().eq(vec![
+                ("critical glitch".to_string(), 1),
+                ("glitch".to_string(), 0),
+                ("failure".to_string(), 3),
+                ("success".to_string(), 2),
+            ]);
+            let test = systems::shadowrun_test(3, 1);
+            test.total().should().eq(216);
+            // two or three 1s and the other die a hit
+            test.get("glitch").should().eq(3 * 2);
+            test.get("critical glitch").should().eq(1 + 3 * 3);
+        }
+
+        #[test]
+        fn blades_action() {
+            systems::blades_action(0).0.should().eq(vec![
+                ("bad outcome".to_string(), 27),
+                ("partial success".to_string(), 8),
+                ("full success".to_string(), 1),
+                ("critical success".to_string(), 0),
+            ]);
+            systems::blades_action(2).0.should().eq(vec![
+                ("bad outcome".to_string(), 9),
+                ("partial success".to_string(), 16),
+                ("full success".to_string(), 10),
+                ("critical success".to_string(), 1),
+            ]);
+        }
+
+        #[test]
+        fn pbta_move() {
+            let roll = systems::pbta_move(1);
+            roll.get("miss").should().eq(10);
+            roll.get("weak hit").should().eq(16);
+            roll.get("strong hit").should().eq(10);
+            roll.get("critical").should().eq(0);
+        }
+    }
+
+    #[cfg(test)]
+    mod bands {
+        use shoulda::Shoulda;
+
+        use super::dice;
+        use crate::bands::Bands;
+        use crate::distribution::Distribution;
+
+        #[test]
+        fn categorize() {
+            let bands = Bands::new([(10, "weak hit"), (7, "miss"), (12, "strong hit")]);
+            bands.band(2).should().eq(0);
+            bands.band(10).should().eq(1);
+            Distribution::from(dice(2, 6))
+                .categorize(&bands)
+                .0
+                .should()
+                .eq(vec![
+                    ("miss".to_string(), 1 + 2 + 3 + 4 + 5 + 6 + 5 + 4),
+                    ("weak hit".to_string(), 3 + 2),
+                    ("strong hit".to_string(), 1),
+                ]);
+        }
+
+        #[test]
+        fn categorize_natural() {
+            let bands = Bands::new([(0, "failure"), (15, "success"), (25, "critical success")]);
+            let outcomes = bands.categorize_natural(&dice(1, 20).into(), &dice(1, 4).into(), 1, 20);
+            outcomes.total().should().eq(80);
+            // a 20 is always a critical success and a 1 always a failure
+            outcomes.get("critical success").should().eq(4);
+            outcomes
+                .get("failure")
+                .should()
+                .eq(4 + (1..=4).map(|x| 13 - x).sum::<u128>());
+            outcomes.get("success").should().eq(80 - 4 - 4 - 42);
+        }
+    }
+
+    #[cfg(test)]
+    mod export {
+        use shoulda::Shoulda;
+
+        use super::dice;
+        use crate::distribution::Distribution;
+        use crate::export::ImportError;
+
+        #[test]
+        fn csv() {
+            let dist: Distribution = dice(1, 4).into();
+            dist.to_csv()
+                .should()
+                .eq("outcome,count,probability,at_least,at_most
+1,1,0.25,1,0.25
+2,1,0.25,0.75,0.5
+3,1,0.25,0.5,0.75
+4,1,0.25,0.25,1
+"
+                .to_string());
+        }
+
+        #[test]
+        fn csv_round_trip() {
+            let dist: Distribution = dice(4, 6).keep_highest(3).into();
+            Distribution::from_csv(&dist.to_csv())
+                .unwrap()
+                .should()
+                .eq(dist);
+        }
+
+        #[test]
+        fn anydice() {
+            let dist: Distribution = dice(1, 4).into();
+            dist.to_anydice("output \"1\"")
+                .should()
+                .eq(r#""output ""1""",2.5,1.11803398875,1,4
+#,%
+1,25
+2,25
+3,25
+4,25
+"#
+                .to_string());
+        }
+
+        #[test]
+        fn anydice_import() {
+            let export = r#""output 1",3.5,1.70782512766,1,6
+#,%
+1,16.6666666667
+2,16.6666666667
+3,16.6666666667
+4,16.6666666667
+5,16.6666666667
+6,16.6666666667
+
+"output 2",7,2.41522945769,2,12
+#,%
+2,2.77777777778
+3,5.55555555556
+4,8.33333333333
+5,11.1111111111
+6,13.8888888889
+7,16.6666666667
+8,13.8888888889
+9,11.1111111111
+10,8.33333333333
+11,5.55555555556
+12,2.77777777778
+"#;
+            let outputs = Distribution::from_anydice(export).unwrap();
+            outputs.len().should().eq(2);
+            outputs[0].0.should().eq("output 1".to_string());
+            outputs[0]
+                .1
+                .clone()
+                .should()
+                .eq(Distribution::from(dice(1, 6)));
+            outputs[1].0.should().eq("output 2".to_string());
+            outputs[1]
+                .1
+                .clone()
+                .should()
+                .eq(Distribution::from(dice(2, 6)));
+        }
+
+        #[test]
+        fn anydice_round_trip() {
+            for dist in [dice(3, 6), dice(4, 6).keep_highest(3), dice(5, 10)] {
+                let dist: Distribution = dist.into();
+                let outputs = Distribution::from_anydice(&dist.to_anydice("x")).unwrap();
+                outputs[0].1.clone().should().eq(dist);
+            }
+        }
+
+        #[test]
+        fn malformed() {
+            Distribution::from_anydice("\"x\"\n#,%\n1,50\n2,fifty\n")
+                .unwrap_err()
+                .should()
+                .eq(ImportError::Malformed(4));
+        }
+    }
+
+    #[cfg(all(test, feature = "serde"))]
+    mod serde {
+        use shoulda::Shoulda;
+
+        use crate::dice::Dice;
+        use crate::distribution::Distribution;
+        use crate::posibility_space::PosibilitySpace;
+
+        #[test]
+        fn distribution_is_sorted() {
+            let dist: Distribution = (PosibilitySpace::from(Dice(3)) + Dice(2).into()).into();
+            serde_json::to_string(&dist).unwrap().should().eq(
+                r#"{"outcomes":[{"outcome":2,"count":1},{"outcome":3,"count":2},{"outcome":4,"count":2},{"outcome":5,"count":1}]}"#
+                    .to_string(),
+            );
+        }
+
+        #[test]
+        fn posibility_space_round_trip() {
+            let space = PosibilitySpace::from(Dice(4)).multiply(2);
+            let json = serde_json::to_string(&space).unwrap();
+            json.starts_with(
+                r#"{"possibilities":[{"dice":[1,1],"count":1},{"dice":[1,2],"count":2}"#,
+            )
+            .should()
+            .be_true();
+            serde_json::from_str::<PosibilitySpace>(&json)
+                .unwrap()
+                .should()
+                .eq(space);
+        }
+
+        #[test]
+        fn large_counts_are_strings() {
+            let dist = Distribution([(1, 1 << 53), (2, (1 << 53) - 1)].into());
+            let json = serde_json::to_string(&dist).unwrap();
+            json.should().eq(
+                r#"{"outcomes":[{"outcome":1,"count":"9007199254740992"},{"outcome":2,"count":9007199254740991}]}"#
+                    .to_string(),
+            );
+            serde_json::from_str::<Distribution>(&json)
+                .unwrap()
+                .should()
+                .eq(dist);
+        }
+
+        #[test]
+        fn duplicate_outcomes_are_rejected() {
+            serde_json::from_str::<Distribution>(
+                r#"{"outcomes":[{"outcome":1,"count":1},{"outcome":1,"count":2}]}"#,
+            )
+            .is_err()
+            .should()
+            .be_true();
+        }
+    }
+}
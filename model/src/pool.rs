@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use shoulda::Shoulda;
+
+use crate::{distribution::Distribution, posibility_space::PosibilitySpace, BigInt, Int};
+
+/// What every face of a die is worth when it is rolled in a pool, like 1 for a success, 2 for a
+/// critical and -1 for a failure. Faces that aren't in the table are worth nothing
+#[derive(Clone, Debug, Default, Shoulda)]
+pub struct Scoring(pub HashMap<Int, Int>);
+
+impl Scoring {
+    pub fn score(&self, face: Int) -> Int {
+        self.0.get(&face).copied().unwrap_or_default()
+    }
+}
+
+/// The net score of a pool and how often it botched, which is rolling no successes and at least
+/// one failure
+#[derive(Clone, Debug, Shoulda)]
+pub struct PoolScore {
+    pub net: Distribution,
+    pub botch: BigInt,
+}
+
+impl PoolScore {
+    /// the net score and whether any die scored above and below 0 of every roll
+    fn tally(rolls: impl IntoIterator<Item = ((Int, bool, bool), BigInt)>) -> Self {
+        let mut net = HashMap::new();
+        let mut botch = 0;
+        for ((score, succeeded, failed), amount) in rolls {
+            *net.entry(score).or_insert(0) += amount;
+            if failed && !succeeded {
+                botch += amount;
+            }
+        }
+        Self {
+            net: Distribution(net),
+            botch,
+        }
+    }
+
+    pub fn total(&self) -> BigInt {
+        self.net.total()
+    }
+
+    pub fn botch_probability(&self) -> f64 {
+        self.botch as f64 / self.total() as f64
+    }
+}
+
+impl PosibilitySpace {
+    /// every roll of the pool scored die by die
+    pub fn score(&self, scoring: &Scoring) -> PoolScore {
+        PoolScore::tally(self.0.iter().map(|(pos, amount)| {
+            let scores = pos.iter().map(|x| scoring.score(*x));
+            let key = scores.fold((0, false, false), |(net, succeeded, failed), x| {
+                (net + x, succeeded || x > 0, failed || x < 0)
+            });
+            (key, *amount)
+        }))
+    }
+}
+
+impl Distribution {
+    /// rolling `dice` of this die scored die by die, like [`PosibilitySpace::score`] but only
+    /// keeping track of the score instead of every way the dice can be rolled
+    pub fn score_pool(&self, dice: usize, scoring: &Scoring) -> PoolScore {
+        let mut rolls = HashMap::from([((0, false, false), 1)]);
+        for _ in 0..dice {
+            let mut new = HashMap::with_capacity(rolls.len());
+            for ((net, succeeded, failed), amount) in rolls {
+                for (face, amount_face) in self.0.iter() {
+                    let x = scoring.score(*face);
+                    let key = (net + x, succeeded || x > 0, failed || x < 0);
+                    *new.entry(key).or_insert(0) += amount * amount_face;
+                }
+            }
+            rolls = new;
+        }
+        PoolScore::tally(rolls)
+    }
+}
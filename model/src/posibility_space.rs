@@ -94,7 +94,7 @@ impl Add for PosibilitySpace {
                 let mut pos = pos_x
                     .clone()
                     .into_iter()
-                    .chain(pos_y.clone().into_iter())
+                    .chain(pos_y.clone())
                     .collect::<Vec<_>>();
                 pos.sort_unstable();
                 let amount = (amount_x * amount_y) + *new.get(&pos).unwrap_or(&0);
@@ -0,0 +1,33 @@
+use crate::BigInt;
+
+/// A small seedable pseudo random number generator (splitmix64), so rolls can be reproduced on any
+/// platform without pulling in an entropy source
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// a uniformly distributed number in `0..n`
+    pub fn below(&mut self, n: BigInt) -> BigInt {
+        assert!(n > 0, "can't pick a number below 0");
+        // reject the top slice of the range that would make lower numbers more likely
+        let zone = BigInt::MAX - BigInt::MAX % n;
+        loop {
+            let x = ((self.next_u64() as BigInt) << 64) | self.next_u64() as BigInt;
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Serialization of [`Distribution`] and [`PosibilitySpace`] with a stable layout, entries are
+//! sorted and counts above what a javascript number can hold exactly are written as strings
+//!
+//! ```json
+//! {"outcomes":[{"outcome":1,"count":1},{"outcome":2,"count":1}]}
+//! {"possibilities":[{"dice":[1,1],"count":1},{"dice":[1,2],"count":2}]}
+//! ```
+
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{distribution::Distribution, posibility_space::PosibilitySpace, BigInt, Int};
+
+/// the largest integer a double holds exactly
+const MAX_SAFE_INTEGER: BigInt = (1 << 53) - 1;
+
+mod count {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(count: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        if *count <= MAX_SAFE_INTEGER {
+            serializer.serialize_u64(*count as u64)
+        } else {
+            serializer.serialize_str(&count.to_string())
+        }
+    }
+
+    struct CountVisitor;
+
+    impl de::Visitor<'_> for CountVisitor {
+        type Value = BigInt;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a non-negative integer or a string holding one")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<BigInt, E> {
+            Ok(v as BigInt)
+        }
+
+        fn visit_u128<E: de::Error>(self, v: u128) -> Result<BigInt, E> {
+            Ok(v)
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<BigInt, E> {
+            BigInt::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<BigInt, E> {
+            v.parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        deserializer.deserialize_any(CountVisitor)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Outcome {
+    outcome: Int,
+    #[serde(with = "count")]
+    count: BigInt,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Outcomes {
+    outcomes: Vec<Outcome>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Possibility {
+    dice: Vec<Int>,
+    #[serde(with = "count")]
+    count: BigInt,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Possibilities {
+    possibilities: Vec<Possibility>,
+}
+
+impl Serialize for Distribution {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Outcomes {
+            outcomes: self
+                .sorted()
+                .into_iter()
+                .map(|(outcome, count)| Outcome { outcome, count })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Distribution {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let outcomes = Outcomes::deserialize(deserializer)?.outcomes;
+        let len = outcomes.len();
+        let dist = Distribution(outcomes.into_iter().map(|x| (x.outcome, x.count)).collect());
+        if dist.0.len() != len {
+            return Err(de::Error::custom("duplicate outcome"));
+        }
+        Ok(dist)
+    }
+}
+
+impl Serialize for PosibilitySpace {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut possibilities = self
+            .0
+            .iter()
+            .map(|(dice, count)| Possibility {
+                dice: dice.clone(),
+                count: *count,
+            })
+            .collect::<Vec<_>>();
+        possibilities.sort_by(|a, b| a.dice.cmp(&b.dice));
+        Possibilities { possibilities }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PosibilitySpace {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let possibilities = Possibilities::deserialize(deserializer)?.possibilities;
+        let len = possibilities.len();
+        let space = PosibilitySpace(
+            possibilities
+                .into_iter()
+                .map(|x| (x.dice, x.count))
+                .collect(),
+        );
+        if space.0.len() != len {
+            return Err(de::Error::custom("duplicate dice"));
+        }
+        Ok(space)
+    }
+}
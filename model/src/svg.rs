@@ -0,0 +1,219 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{distribution::Distribution, histogram::Mode, Int};
+
+/// How each distribution is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Style {
+    #[default]
+    Bar,
+    Line,
+}
+
+/// Renders one or more [`Distribution`]s as an svg chart with labelled axes, distributions are
+/// overlaid on the same axes and named in a legend when there is more than one
+#[derive(Clone, Debug)]
+pub struct Chart {
+    pub width: u32,
+    pub height: u32,
+    pub style: Style,
+    pub mode: Mode,
+    pub title: Option<String>,
+}
+
+impl Default for Chart {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 400,
+            style: Style::default(),
+            mode: Mode::default(),
+            title: None,
+        }
+    }
+}
+
+const COLORS: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+];
+const LEFT: f64 = 60.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 40.0;
+const BOTTOM: f64 = 50.0;
+const Y_TICKS: usize = 5;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// the probability of each of `outcomes` in `dist`, according to `mode`. `outcomes` is sorted,
+/// and in [`Mode::Exact`] the ones `dist` can't roll have no point
+fn points(dist: &Distribution, mode: Mode, outcomes: &[Int]) -> Vec<Option<f64>> {
+    let total = dist.total();
+    let mut below = 0;
+    outcomes
+        .iter()
+        .map(|x| {
+            let amount = *dist.0.get(x).unwrap_or(&0);
+            let value = match mode {
+                Mode::Exact if amount == 0 => None,
+                Mode::Exact => Some(amount),
+                Mode::AtLeast => Some(total - below),
+                Mode::AtMost => Some(below + amount),
+            };
+            below += amount;
+            value.map(|x| x as f64 / total as f64)
+        })
+        .collect()
+}
+
+impl Chart {
+    pub fn render(&self, series: &[(&str, &Distribution)]) -> String {
+        // only the outcomes that can be rolled get a slot, so sparse distributions stay small
+        let outcomes = series
+            .iter()
+            .flat_map(|(_, x)| x.0.keys().copied())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let values = series
+            .iter()
+            .map(|(_, x)| points(x, self.mode, &outcomes))
+            .collect::<Vec<_>>();
+        let top = values
+            .iter()
+            .flatten()
+            .flatten()
+            .fold(0.0f64, |acc, x| acc.max(*x))
+            .max(f64::EPSILON);
+        // round the top of the y axis up to a whole percent that divides into the ticks
+        let top = ((top * 100.0 / Y_TICKS as f64).ceil() * Y_TICKS as f64 / 100.0).min(1.0);
+
+        let (width, height) = (self.width as f64, self.height as f64);
+        let plot_width = width - LEFT - RIGHT;
+        let plot_height = height - TOP - BOTTOM;
+        let slots = outcomes.len().max(1);
+        let slot = plot_width / slots as f64;
+        let x_of = |i: usize| LEFT + slot * (i as f64 + 0.5);
+        let y_of = |p: f64| TOP + plot_height * (1.0 - p / top);
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="12">"#,
+            self.width, self.height, self.width, self.height
+        );
+        let _ = write!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="white"/>"#,
+            self.width, self.height
+        );
+        if let Some(title) = &self.title {
+            let _ = write!(
+                svg,
+                r#"<text x="{:.2}" y="{:.2}" text-anchor="middle" font-size="16">{}</text>"#,
+                width / 2.0,
+                TOP / 2.0 + 6.0,
+                escape(title)
+            );
+        }
+
+        for i in 0..=Y_TICKS {
+            let p = top * i as f64 / Y_TICKS as f64;
+            let y = y_of(p);
+            let _ = write!(
+                svg,
+                r##"<line x1="{LEFT:.2}" y1="{y:.2}" x2="{:.2}" y2="{y:.2}" stroke="#ddd"/><text x="{:.2}" y="{:.2}" text-anchor="end">{:.0}%</text>"##,
+                width - RIGHT,
+                LEFT - 6.0,
+                y + 4.0,
+                p * 100.0
+            );
+        }
+        // label every outcome when they fit, otherwise every nth
+        let step = ((slots as f64 * 28.0 / plot_width).ceil() as usize).max(1);
+        for i in (0..outcomes.len()).step_by(step) {
+            let _ = write!(
+                svg,
+                r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">{}</text>"#,
+                x_of(i),
+                TOP + plot_height + 16.0,
+                outcomes[i]
+            );
+        }
+
+        let bar_width = slot * 0.8 / series.len().max(1) as f64;
+        for (n, values) in values.iter().enumerate() {
+            let color = COLORS[n % COLORS.len()];
+            match self.style {
+                Style::Bar => {
+                    for (i, p) in values.iter().enumerate() {
+                        let Some(p) = p else { continue };
+                        let x = LEFT + slot * (i as f64 + 0.1) + bar_width * n as f64;
+                        let y = y_of(*p);
+                        let _ = write!(
+                            svg,
+                            r#"<rect class="bar" x="{x:.2}" y="{y:.2}" width="{bar_width:.2}" height="{:.2}" fill="{color}"/>"#,
+                            TOP + plot_height - y
+                        );
+                    }
+                }
+                Style::Line => {
+                    let points = values
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, p)| p.map(|p| format!("{:.2},{:.2}", x_of(i), y_of(p))))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let _ = write!(
+                        svg,
+                        r#"<polyline class="line" points="{points}" fill="none" stroke="{color}" stroke-width="2"/>"#
+                    );
+                }
+            }
+        }
+
+        let _ = write!(
+            svg,
+            r#"<line x1="{LEFT:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="black"/><line x1="{LEFT:.2}" y1="{TOP:.2}" x2="{LEFT:.2}" y2="{:.2}" stroke="black"/>"#,
+            TOP + plot_height,
+            width - RIGHT,
+            TOP + plot_height,
+            TOP + plot_height
+        );
+        let y_label = match self.mode {
+            Mode::Exact => "probability",
+            Mode::AtLeast => "probability of at least",
+            Mode::AtMost => "probability of at most",
+        };
+        let _ = write!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">outcome</text><text x="14" y="{:.2}" text-anchor="middle" transform="rotate(-90 14 {:.2})">{y_label}</text>"#,
+            LEFT + plot_width / 2.0,
+            height - 10.0,
+            TOP + plot_height / 2.0,
+            TOP + plot_height / 2.0
+        );
+
+        if series.len() > 1 {
+            for (n, (name, _)) in series.iter().enumerate() {
+                let y = TOP + 8.0 + 18.0 * n as f64;
+                let x = width - RIGHT - 150.0;
+                let _ = write!(
+                    svg,
+                    r#"<rect class="legend" x="{x:.2}" y="{:.2}" width="12" height="12" fill="{}"/><text x="{:.2}" y="{:.2}">{}</text>"#,
+                    y - 10.0,
+                    COLORS[n % COLORS.len()],
+                    x + 18.0,
+                    y,
+                    escape(name)
+                );
+            }
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
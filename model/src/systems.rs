@@ -0,0 +1,159 @@
+use shoulda::Shoulda;
+
+use crate::{
+    bands::Bands, dice::Dice, distribution::Distribution, joint_distribution::JointDistribution,
+    posibility_space::PosibilitySpace, BigInt, Int,
+};
+
+/// How many ways a roll can end up as each labelled outcome, from the worst outcome to the best
+#[derive(Clone, Debug, Shoulda)]
+pub struct Outcomes(pub Vec<(String, BigInt)>);
+
+impl Outcomes {
+    /// every roll counted towards the label at its index
+    pub(crate) fn tally(labels: &[&str], rolls: impl IntoIterator<Item = (usize, BigInt)>) -> Self {
+        let mut counts = vec![0; labels.len()];
+        for (label, amount) in rolls {
+            counts[label] += amount;
+        }
+        Self(labels.iter().map(|x| x.to_string()).zip(counts).collect())
+    }
+
+    pub fn total(&self) -> BigInt {
+        self.0.iter().map(|(_, amount)| amount).sum()
+    }
+
+    /// how many ways there are to roll `label`, 0 when it isn't one of the outcomes
+    pub fn get(&self, label: &str) -> BigInt {
+        self.0
+            .iter()
+            .find(|(x, _)| x == label)
+            .map_or(0, |(_, amount)| *amount)
+    }
+
+    pub fn probability(&self, label: &str) -> f64 {
+        self.get(label) as f64 / self.total() as f64
+    }
+}
+
+/// how a d20 is rolled in D&D 5e
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Advantage {
+    #[default]
+    Normal,
+    /// the higher of two d20s
+    Advantage,
+    /// the lower of two d20s
+    Disadvantage,
+}
+
+impl Advantage {
+    pub fn d20(&self) -> Distribution {
+        let d20 = PosibilitySpace::from(Dice(20));
+        match self {
+            Advantage::Normal => d20.into(),
+            Advantage::Advantage => d20.multiply(2).keep_highest(1).into(),
+            Advantage::Disadvantage => d20.multiply(2).keep_lowest(1).into(),
+        }
+    }
+}
+
+/// a D&D 5e attack roll of a d20 plus `bonus` against armor class `ac`, a 20 on the die always
+/// hits and is a critical hit while a 1 always misses
+pub fn dnd5e_attack(bonus: Int, ac: Int, advantage: Advantage) -> Outcomes {
+    Outcomes::tally(
+        &["miss", "hit", "critical hit"],
+        advantage.d20().0.into_iter().map(|(face, amount)| {
+            let outcome = match face {
+                20 => 2,
+                1 => 0,
+                _ => (face + bonus >= ac) as usize,
+            };
+            (outcome, amount)
+        }),
+    )
+}
+
+/// a Pathfinder 2e check of a d20 plus `modifier` against `dc`, beating it by 10 is a critical
+/// success and missing it by 10 a critical failure. A 20 on the die makes it one degree better
+/// and a 1 one degree worse
+pub fn pf2e_check(modifier: Int, dc: Int) -> Outcomes {
+    let bands = Bands::new([
+        (Int::MIN, "critical failure"),
+        (dc.saturating_sub(9), "failure"),
+        (dc, "success"),
+        (dc.saturating_add(10), "critical success"),
+    ]);
+    let d20 = Distribution::from(PosibilitySpace::from(Dice(20)));
+    bands.categorize_natural(&d20, &Distribution([(modifier, 1)].into()), 1, 20)
+}
+
+/// a Shadowrun test of `dice` d6 that needs `threshold` hits, a 5 or 6 is a hit. More than half
+/// the dice showing a 1 is a glitch, whether the test succeeds or not, and a critical glitch
+/// when there are no hits at all
+pub fn shadowrun_test(dice: usize, threshold: usize) -> Outcomes {
+    // how many hits and how many 1s every face counts as
+    let d6 = Dice(6).symbols(|x| vec![(x >= 5) as Int, (x == 1) as Int]);
+    let mut pool = JointDistribution([(vec![0, 0], 1)].into());
+    for _ in 0..dice {
+        pool = pool.sum(&d6);
+    }
+    Outcomes::tally(
+        &["critical glitch", "glitch", "failure", "success"],
+        pool.0.into_iter().map(|(x, amount)| {
+            let (hits, ones) = (x[0] as usize, x[1] as usize);
+            let outcome = match (ones * 2 > dice, hits) {
+                (true, 0) => 0,
+                (true, _) => 1,
+                (false, hits) => 2 + (hits >= threshold) as usize,
+            };
+            (outcome, amount)
+        }),
+    )
+}
+
+/// a Blades in the Dark action roll of `dice` d6 where the highest die counts, 6 is a full
+/// success and more than one 6 a critical. Without any dice it's the lowest of 2d6 instead, which
+/// can't be a critical
+pub fn blades_action(dice: usize) -> Outcomes {
+    let d6 = PosibilitySpace::from(Dice(6));
+    let rolls = match dice {
+        0 => d6.multiply(2).keep_lowest(1),
+        dice => d6.multiply(dice as BigInt),
+    };
+    Outcomes::tally(
+        &[
+            "bad outcome",
+            "partial success",
+            "full success",
+            "critical success",
+        ],
+        rolls.0.into_iter().map(|(pos, amount)| {
+            let sixes = pos.iter().filter(|x| **x == 6).count();
+            let outcome = match pos.iter().max() {
+                _ if sixes > 1 => 3,
+                Some(6) => 2,
+                Some(4 | 5) => 1,
+                _ => 0,
+            };
+            (outcome, amount)
+        }),
+    )
+}
+
+/// a Powered by the Apocalypse move of 2d6 plus `stat`, 10 and up is a strong hit, 7 to 9 a weak
+/// hit and anything less a miss
+pub fn pbta_move(stat: Int) -> Outcomes {
+    let two_d6 = Distribution::from(PosibilitySpace::from(Dice(6)).multiply(2));
+    Outcomes::tally(
+        &["miss", "weak hit", "strong hit"],
+        two_d6.0.into_iter().map(|(x, amount)| {
+            let outcome = match x + stat {
+                10.. => 2,
+                7..=9 => 1,
+                _ => 0,
+            };
+            (outcome, amount)
+        }),
+    )
+}
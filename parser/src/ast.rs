@@ -1,5 +1,13 @@
-use std::{borrow::Cow, error::Error, fmt::Display, ops::Deref, rc::Rc, str::FromStr};
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::Display,
+    ops::{Deref, Range},
+    rc::Rc,
+    str::FromStr,
+};
 
+use model::BigInt;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use shoulda::Shoulda;
@@ -53,13 +61,276 @@ mod tests {
         ))));
     }
 
+    #[test]
+    fn parse_variable() {
+        let value: Value = "d20 + ac".parse().unwrap();
+        value.should().eq(Value::Operator(Rc::new(Operator::Plus(
+            Value::Operator(Rc::new(Operator::Dice(Value::Default, Value::Constant(20)))),
+            Value::Variable("ac".to_string()),
+        ))));
+    }
+
+    #[test]
+    fn parse_variable_containing_operator() {
+        let value: Value = "dmg * 2".parse().unwrap();
+        value
+            .should()
+            .eq(Value::Operator(Rc::new(Operator::Multiply(
+                Value::Variable("dmg".to_string()),
+                Value::Constant(2),
+            ))));
+    }
+
     #[cfg(test)]
     mod weird_syntax {
         use std::rc::Rc;
 
         use shoulda::Shoulda;
 
-        use crate::ast::{Operator, Value};
+        use crate::ast::{Comparison, Function, Operator, Value, ValueParseError, GIVEN};
+
+        #[test]
+        fn negate() {
+            let value: Value = "-d6".parse().unwrap();
+            value
+                .should()
+                .eq(Value::Operator(Rc::new(Operator::Negate(Value::Operator(
+                    Rc::new(Operator::Dice(Value::Default, Value::Constant(6))),
+                )))));
+        }
+
+        #[test]
+        fn unary_plus() {
+            let value: Value = "2 * +d4".parse().unwrap();
+            value
+                .should()
+                .eq(Value::Operator(Rc::new(Operator::Multiply(
+                    Value::Constant(2),
+                    Value::Operator(Rc::new(Operator::Dice(Value::Default, Value::Constant(4)))),
+                ))));
+        }
+
+        #[test]
+        fn minus_negative() {
+            let value: Value = "1 - -2d6".parse().unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::Minus(
+                Value::Constant(1),
+                Value::Operator(Rc::new(Operator::Negate(Value::Operator(Rc::new(
+                    Operator::Dice(Value::Constant(2), Value::Constant(6)),
+                ))))),
+            ))));
+        }
+
+        #[test]
+        fn call() {
+            let value: Value = "max(d20, (1 + 2))".parse().unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::Call(
+                Function::Max,
+                vec![
+                    Value::Operator(Rc::new(Operator::Dice(Value::Default, Value::Constant(20)))),
+                    Value::Operator(Rc::new(Operator::Plus(
+                        Value::Constant(1),
+                        Value::Constant(2),
+                    ))),
+                ],
+            ))));
+        }
+
+        #[test]
+        fn binding() {
+            let value: Value = "let atk = d20 + 5; roll dmg = 2d6; atk + dmg"
+                .parse()
+                .unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::Let(
+                "atk".to_string(),
+                Value::Operator(Rc::new(Operator::Plus(
+                    Value::Operator(Rc::new(Operator::Dice(Value::Default, Value::Constant(20)))),
+                    Value::Constant(5),
+                ))),
+                Value::Operator(Rc::new(Operator::Roll(
+                    "dmg".to_string(),
+                    Value::Operator(Rc::new(Operator::Dice(
+                        Value::Constant(2),
+                        Value::Constant(6),
+                    ))),
+                    Value::Operator(Rc::new(Operator::Plus(
+                        Value::Variable("atk".to_string()),
+                        Value::Variable("dmg".to_string()),
+                    ))),
+                ))),
+            ))));
+        }
+
+        #[test]
+        fn conditional() {
+            let value: Value = "if d20 >= 15 then 2d6 else 0".parse().unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::If(
+                Value::Operator(Rc::new(Operator::Compare(
+                    Comparison::GreaterEqual,
+                    Value::Operator(Rc::new(Operator::Dice(Value::Default, Value::Constant(20)))),
+                    Value::Constant(15),
+                ))),
+                Value::Operator(Rc::new(Operator::Dice(
+                    Value::Constant(2),
+                    Value::Constant(6),
+                ))),
+                Value::Constant(0),
+            ))));
+        }
+
+        #[test]
+        fn count_successes_is_not_a_comparison() {
+            let value: Value = "4d6 cs<= 3 < 2".parse().unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::Compare(
+                Comparison::Less,
+                Value::Operator(Rc::new(Operator::CountSuccesses(
+                    Value::Operator(Rc::new(Operator::Dice(
+                        Value::Constant(4),
+                        Value::Constant(6),
+                    ))),
+                    Value::Operator(Rc::new(Operator::Plus(
+                        Value::Constant(3),
+                        Value::Constant(1),
+                    ))),
+                ))),
+                Value::Constant(2),
+            ))));
+        }
+
+        #[test]
+        fn given() {
+            let value: Value = "(2d6 | 2d6 >= 7)".parse().unwrap();
+            let two_d6 = Value::Operator(Rc::new(Operator::Dice(
+                Value::Constant(2),
+                Value::Constant(6),
+            )));
+            value.should().eq(Value::Operator(Rc::new(Operator::Given(
+                two_d6,
+                Value::Operator(Rc::new(Operator::Compare(
+                    Comparison::GreaterEqual,
+                    Value::Variable(GIVEN.to_string()),
+                    Value::Constant(7),
+                ))),
+            ))));
+        }
+
+        #[test]
+        fn versus() {
+            let value: Value = "d20+3 vs d20 + 5".parse().unwrap();
+            let d20_plus = |bonus| {
+                Value::Operator(Rc::new(Operator::Plus(
+                    Value::Operator(Rc::new(Operator::Dice(Value::Default, Value::Constant(20)))),
+                    Value::Constant(bonus),
+                )))
+            };
+            value.should().eq(Value::Operator(Rc::new(Operator::Versus(
+                d20_plus(3),
+                d20_plus(5),
+            ))));
+            "dvs + 1"
+                .parse::<Value>()
+                .should()
+                .eq(Ok(Value::Operator(Rc::new(Operator::Plus(
+                    Value::Variable("dvs".to_string()),
+                    Value::Constant(1),
+                )))));
+        }
+
+        #[test]
+        fn successes() {
+            let ten_d10 = Value::Operator(Rc::new(Operator::Dice(
+                Value::Constant(10),
+                Value::Constant(10),
+            )));
+            let value: Value = "10d10[>=8] + 1".parse().unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::Plus(
+                Value::Operator(Rc::new(Operator::Successes(
+                    Comparison::GreaterEqual,
+                    ten_d10.clone(),
+                    Value::Constant(8),
+                ))),
+                Value::Constant(1),
+            ))));
+            let value: Value = "6d6[=6]".parse().unwrap();
+            value
+                .should()
+                .eq(Value::Operator(Rc::new(Operator::Successes(
+                    Comparison::Equal,
+                    Value::Operator(Rc::new(Operator::Dice(
+                        Value::Constant(6),
+                        Value::Constant(6),
+                    ))),
+                    Value::Constant(6),
+                ))));
+            let value: Value = "10d10[8..10, 1]".parse().unwrap();
+            value.should().eq(Value::Operator(Rc::new(Operator::Faces(
+                ten_d10.clone(),
+                vec![(8, 10), (1, 1)],
+            ))));
+            // without brackets it compares the total, however it is spaced
+            for source in ["10d10>=8", "10d10 >= 8", "(10d10)>=8"] {
+                let value: Value = source.parse().unwrap();
+                value.should().eq(Value::Operator(Rc::new(Operator::Compare(
+                    Comparison::GreaterEqual,
+                    ten_d10.clone(),
+                    Value::Constant(8),
+                ))));
+            }
+            "10d10 [ >= 8 ]"
+                .parse::<Value>()
+                .should()
+                .eq("10d10[>=8]".parse::<Value>());
+        }
+
+        #[test]
+        fn failures() {
+            let value: Value = "10d10[>=7]f1".parse().unwrap();
+            let ten_d10 = Value::Operator(Rc::new(Operator::Dice(
+                Value::Constant(10),
+                Value::Constant(10),
+            )));
+            value
+                .should()
+                .eq(Value::Operator(Rc::new(Operator::Failures(
+                    Value::Operator(Rc::new(Operator::Successes(
+                        Comparison::GreaterEqual,
+                        ten_d10.clone(),
+                        Value::Constant(7),
+                    ))),
+                    Comparison::Equal,
+                    Value::Constant(1),
+                ))));
+            let value: Value = "10d10[9, 10]f<3".parse().unwrap();
+            value
+                .should()
+                .eq(Value::Operator(Rc::new(Operator::Failures(
+                    Value::Operator(Rc::new(Operator::Faces(ten_d10, vec![(9, 9), (10, 10)]))),
+                    Comparison::Less,
+                    Value::Constant(3),
+                ))));
+            "10d10[>=ff1]"
+                .parse::<Value>()
+                .should()
+                .eq(Ok(Value::Operator(Rc::new(Operator::Successes(
+                    Comparison::GreaterEqual,
+                    Value::Operator(Rc::new(Operator::Dice(
+                        Value::Constant(10),
+                        Value::Constant(10),
+                    ))),
+                    Value::Variable("ff1".to_string()),
+                )))));
+            // the failures need a threshold, it isn't face 0
+            "10d10[>=7]f"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::MissingThreshold("[>=7]f".to_string())));
+            "10d10[>=7]f<="
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::MissingThreshold(
+                    "[>=7]f<=".to_string(),
+                )));
+        }
 
         #[test]
         fn plus_with_negative_number() {
@@ -75,7 +346,7 @@ mod tests {
     mod error {
         use shoulda::Shoulda;
 
-        use crate::ast::{Value, ValueParseError};
+        use crate::ast::{Function, Value, ValueParseError};
 
         #[test]
         fn invalid_parentheses() {
@@ -90,6 +361,397 @@ mod tests {
                 vec!["y".to_string()],
             )));
         }
+
+        #[test]
+        fn invalid_ops_span() {
+            let source = "2d6 + 2y6";
+            let err = source.parse::<Value>().unwrap_err();
+            err.span(source).should().eq(Some(7..8));
+        }
+
+        #[test]
+        fn unknown_function() {
+            let source = "2 + foo(1)";
+            let err = source.parse::<Value>().unwrap_err();
+            err.should()
+                .eq(ValueParseError::UnknownFunction("foo".to_string()));
+        }
+
+        #[test]
+        fn invalid_arguments() {
+            let source = "d20 + clamp(d6, 1)";
+            let err = source.parse::<Value>().unwrap_err();
+            err.span(source).should().eq(Some(6..11));
+            err.to_string()
+                .should()
+                .eq("Invalid Arguments: clamp takes 3 arguments".to_string());
+        }
+
+        #[test]
+        fn empty_arguments() {
+            for (source, function) in [
+                ("max()", Function::Max),
+                ("min(d6, )", Function::Min),
+                ("clamp(d6, , 3)", Function::Clamp),
+            ] {
+                let err = source.parse::<Value>().unwrap_err();
+                err.should().eq(ValueParseError::InvalidArguments(function));
+            }
+        }
+
+        #[test]
+        fn invalid_binding() {
+            let source = "1 + (let d = 2; d)";
+            let err = source.parse::<Value>().unwrap_err();
+            err.should()
+                .eq(ValueParseError::InvalidBinding("let d = 2".to_string()));
+            err.span(source).should().eq(Some(5..14));
+            "roll x = d6"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::InvalidBinding(
+                    "roll x = d6".to_string(),
+                )));
+        }
+
+        #[test]
+        fn invalid_conditional() {
+            "1 + (if d6 > 3 then 1)".parse::<Value>().should().eq(Err(
+                ValueParseError::InvalidConditional("if d6 > 3 then 1".to_string()),
+            ));
+        }
+
+        #[test]
+        fn invalid_weight() {
+            let source = "mix(1: d6, 1 / 0: d8)";
+            let err = source.parse::<Value>().unwrap_err();
+            err.should()
+                .eq(ValueParseError::InvalidWeight("1 / 0".to_string()));
+            err.span(source).should().eq(Some(11..16));
+            "mix(d6)"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::InvalidWeight("d6".to_string())));
+            "mix(0: d6)"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::InvalidWeight("(0: d6)".to_string())));
+            "mix(1: d6, 1:)"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::InvalidWeight("1:".to_string())));
+        }
+
+        #[test]
+        fn versus_without_operand() {
+            let source = "d6 vs";
+            let err = source.parse::<Value>().unwrap_err();
+            err.should()
+                .eq(ValueParseError::InvalidOperators(vec!["vs".to_string()]));
+            err.span(source).should().eq(Some(3..5));
+            "vs d6"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::InvalidOperators(vec![
+                    "vs".to_string()
+                ])));
+        }
+
+        #[test]
+        fn invalid_faces() {
+            let source = "1 + 5d10[9..8]";
+            let err = source.parse::<Value>().unwrap_err();
+            err.should()
+                .eq(ValueParseError::InvalidFaces("[9..8]".to_string()));
+            err.span(source).should().eq(Some(8..14));
+            "5d10[8..]"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::InvalidFaces("[8..]".to_string())));
+        }
+
+        #[test]
+        fn missing_threshold() {
+            let source = "1 + d6[>=]";
+            let err = source.parse::<Value>().unwrap_err();
+            err.should()
+                .eq(ValueParseError::MissingThreshold("[>=]".to_string()));
+            err.span(source).should().eq(Some(6..10));
+            "6d6[=]"
+                .parse::<Value>()
+                .should()
+                .eq(Err(ValueParseError::MissingThreshold("[=]".to_string())));
+        }
+
+        #[test]
+        fn invalid_parentheses_span() {
+            let source = "2 + (((1)) * 3";
+            let err = source.parse::<Value>().unwrap_err();
+            err.span(source).should().eq(Some(4..5));
+        }
+    }
+
+    #[cfg(all(test, feature = "serde"))]
+    mod serde {
+        use shoulda::Shoulda;
+
+        use crate::ast::Value;
+
+        #[test]
+        fn serialize() {
+            let value: Value = "2d6 + x".parse().unwrap();
+            serde_json::to_string(&value).unwrap().should().eq(
+                r#"{"operator":{"plus":[{"operator":{"dice":[{"constant":2},{"constant":6}]}},{"variable":"x"}]}}"#
+                    .to_string(),
+            );
+        }
+
+        #[test]
+        fn round_trip() {
+            let value: Value = "(4d6kh3 - 1) * 2 / d".parse().unwrap();
+            let json = serde_json::to_string(&value).unwrap();
+            serde_json::from_str::<Value>(&json)
+                .unwrap()
+                .should()
+                .eq(value);
+        }
+    }
+
+    #[cfg(test)]
+    mod display {
+        use std::rc::Rc;
+
+        use model::rng::Rng;
+        use shoulda::Shoulda;
+
+        use crate::ast::{Comparison, Function, Operator, Value, Weight};
+
+        fn canonical(s: &str) -> String {
+            s.parse::<Value>().unwrap().to_string()
+        }
+
+        #[test]
+        fn minimal_parentheses() {
+            canonical("((2d6)) + (3)")
+                .should()
+                .eq("2d6 + 3".to_string());
+            canonical("(1 + 2) * 3")
+                .should()
+                .eq("(1 + 2) * 3".to_string());
+            canonical("1 + (2 * 3)")
+                .should()
+                .eq("1 + 2 * 3".to_string());
+            canonical("(1 - 2) - 3")
+                .should()
+                .eq("1 - 2 - 3".to_string());
+            canonical("1 - (2 - 3)")
+                .should()
+                .eq("1 - (2 - 3)".to_string());
+            canonical("(4 d 6) kh 3").should().eq("4d6kh3".to_string());
+            canonical("2d(1d4)").should().eq("2d(1d4)".to_string());
+        }
+
+        #[test]
+        fn defaults_and_variables() {
+            canonical("d20 + dmg").should().eq("d20 + dmg".to_string());
+            canonical("x d 6").should().eq("(x)d6".to_string());
+            canonical("2 * -3").should().eq("2 * (-3)".to_string());
+            canonical("--3").should().eq("-(-3)".to_string());
+            canonical("-(1d4 + 1)")
+                .should()
+                .eq("-(1d4 + 1)".to_string());
+            canonical("-3").should().eq("-3".to_string());
+            canonical("").should().eq("".to_string());
+        }
+
+        #[test]
+        fn bindings() {
+            canonical("let x=d6;x+x")
+                .should()
+                .eq("let x = d6; x + x".to_string());
+            canonical("2 * (roll x = d6; x)")
+                .should()
+                .eq("2 * (roll x = d6; x)".to_string());
+            canonical("let x = (let y = 1; y); let z = 2; x")
+                .should()
+                .eq("let x = (let y = 1; y); let z = 2; x".to_string());
+        }
+
+        #[test]
+        fn conditionals() {
+            canonical("if(d20>=15)then 2d6 else(0)")
+                .should()
+                .eq("if d20 >= 15 then 2d6 else 0".to_string());
+            canonical("if x then if y then 1 else 2 else 3")
+                .should()
+                .eq("if x then (if y then 1 else 2) else 3".to_string());
+            canonical("(d6 + 1 > 2) * 3")
+                .should()
+                .eq("(d6 + 1 > 2) * 3".to_string());
+        }
+
+        #[test]
+        fn given() {
+            canonical("(2d6 | 2d6 >= 7)")
+                .should()
+                .eq("2d6 | 2d6 >= 7".to_string());
+            canonical("d20 | (d20 | d20 > 5) > 10")
+                .should()
+                .eq("d20 | (d20 | d20 > 5) > 10".to_string());
+        }
+
+        #[test]
+        fn versus() {
+            canonical("(d20+3)vs(d20+5)")
+                .should()
+                .eq("d20 + 3 vs d20 + 5".to_string());
+            canonical("(d6 vs d6) == 1")
+                .should()
+                .eq("d6 vs d6 == 1".to_string());
+            canonical("vs vs versus")
+                .should()
+                .eq("vs vs versus".to_string());
+        }
+
+        #[test]
+        fn successes() {
+            canonical("(10d10[>=8])+1")
+                .should()
+                .eq("10d10[>=8] + 1".to_string());
+            canonical("6d6[=6]").should().eq("6d6[==6]".to_string());
+            canonical("-4d6kh3[>=(1+1)]")
+                .should()
+                .eq("-4d6kh3[>=1 + 1]".to_string());
+            canonical("5d10[ 8..=10,1 ]")
+                .should()
+                .eq("5d10[8..10, 1]".to_string());
+            canonical("(10d10[>=7]f=1)*2")
+                .should()
+                .eq("10d10[>=7]f1 * 2".to_string());
+            canonical("10d10[10]f<=(-1)")
+                .should()
+                .eq("10d10[10]f<=(-1)".to_string());
+        }
+
+        #[test]
+        fn mix() {
+            canonical("mix(30%: d8, 0.70: 2d6, 2/4: (1 + 1))")
+                .should()
+                .eq("mix(3/10: d8, 7/10: 2d6, 1/2: 1 + 1)".to_string());
+        }
+
+        /// `x`, or a constant in place of an empty value where the syntax needs one
+        fn filled(x: Value) -> Value {
+            match x {
+                Value::Default => Value::Constant(1),
+                x => x,
+            }
+        }
+
+        fn random_value(rng: &mut Rng, depth: usize) -> Value {
+            const NAMES: [&str; 5] = ["x", "dmg", "kha", "d_2", "bonus"];
+            let pick = |rng: &mut Rng, n: usize| rng.below(n as u128) as usize;
+            if depth == 0 || pick(rng, 3) == 0 {
+                return match pick(rng, 6) {
+                    0 => Value::Default,
+                    1 => Value::Variable(NAMES[pick(rng, NAMES.len())].to_string()),
+                    2 => Value::Constant(-(pick(rng, 20) as i32)),
+                    _ => Value::Constant(pick(rng, 100) as i32),
+                };
+            }
+            let l = random_value(rng, depth - 1);
+            let r = random_value(rng, depth - 1);
+            let op = match pick(rng, 17) {
+                // a leading `-` or `+` is a sign, so those never have an empty left side
+                0 | 1 if matches!(l, Value::Default) => Operator::Negate(r),
+                0 => Operator::Plus(l, r),
+                1 => Operator::Minus(l, r),
+                2 => Operator::Multiply(l, r),
+                3 => Operator::Divide(l, r),
+                4 => Operator::CountSuccesses(l, r),
+                5 => Operator::KeepHighest(l, r),
+                6 => Operator::Dice(l, r),
+                7 => Operator::Negate(l),
+                8 => Operator::Let(NAMES[pick(rng, NAMES.len())].to_string(), l, r),
+                9 => Operator::Roll(NAMES[pick(rng, NAMES.len())].to_string(), l, r),
+                10 => {
+                    let comparison = [
+                        Comparison::Equal,
+                        Comparison::NotEqual,
+                        Comparison::LessEqual,
+                        Comparison::GreaterEqual,
+                        Comparison::Less,
+                        Comparison::Greater,
+                    ][pick(rng, 6)];
+                    Operator::Compare(comparison, l, r)
+                }
+                11 => Operator::If(l, r, random_value(rng, depth - 1)),
+                12 => Operator::given(l, r),
+                13 => Operator::Versus(filled(l), filled(r)),
+                // counting successes is only written after a pool of dice
+                15 => {
+                    let amount = match pick(rng, 3) {
+                        0 => Value::Default,
+                        n => Value::Constant(n as i32 * 3),
+                    };
+                    let pool = Operator::Dice(amount, Value::Constant(1 + pick(rng, 20) as i32));
+                    let pool = Value::Operator(Rc::new(pool));
+                    let comparison = |n| {
+                        [
+                            Comparison::Equal,
+                            Comparison::NotEqual,
+                            Comparison::LessEqual,
+                            Comparison::GreaterEqual,
+                            Comparison::Less,
+                            Comparison::Greater,
+                        ][n]
+                    };
+                    let successes = match pick(rng, 7) {
+                        6 => Operator::Faces(pool, vec![(1, 3), (-2, -2)]),
+                        n => Operator::Successes(comparison(n), pool, filled(r)),
+                    };
+                    match pick(rng, 7) {
+                        6 => successes,
+                        n => Operator::Failures(
+                            Value::Operator(Rc::new(successes)),
+                            comparison(n),
+                            filled(random_value(rng, depth - 1)),
+                        ),
+                    }
+                }
+                14 => {
+                    let mut weight =
+                        || Weight::new(1 + pick(rng, 5) as u128, 1 + pick(rng, 4) as u128);
+                    let mut parts = vec![(weight(), filled(l)), (weight(), filled(r))];
+                    parts.truncate(1 + pick(rng, 2));
+                    Operator::Mix(parts)
+                }
+                _ => {
+                    let function = Function::ALL[pick(rng, Function::ALL.len())];
+                    let extra = function.max_arguments().min(3) - function.min_arguments();
+                    let mut args = vec![filled(l), filled(r)];
+                    args.resize_with(function.min_arguments() + pick(rng, extra + 1), || {
+                        filled(random_value(rng, depth - 1))
+                    });
+                    Operator::Call(function, args)
+                }
+            };
+            Value::Operator(Rc::new(op))
+        }
+
+        #[test]
+        fn round_trip() {
+            let mut rng = Rng::new(35);
+            for _ in 0..2000 {
+                let value = random_value(&mut rng, 5);
+                let source = value.to_string();
+                let parsed = source.parse::<Value>();
+                match parsed {
+                    Ok(parsed) => parsed.should().eq(value),
+                    Err(err) => panic!("{source} failed to parse: {err}"),
+                };
+            }
+        }
     }
 
     #[cfg(test)]
@@ -123,6 +785,19 @@ mod tests {
 pub enum ValueParseError {
     InvalidOperators(Vec<String>),
     InvalidParentheses,
+    UnknownFunction(String),
+    InvalidArguments(Function),
+    /// a `let` or `roll` statement that isn't `let name = value;`
+    InvalidBinding(String),
+    /// an `if` that isn't `if condition then value else value`
+    InvalidConditional(String),
+    /// a part of `mix` without a weight or a value, a weight that isn't a number or only weights
+    /// of 0
+    InvalidWeight(String),
+    /// faces of a die in `pool[..]` that aren't numbers or ranges of numbers
+    InvalidFaces(String),
+    /// a test after a pool of dice, like `>=` or `f`, without anything to compare with
+    MissingThreshold(String),
 }
 
 impl Display for ValueParseError {
@@ -136,38 +811,309 @@ impl Display for ValueParseError {
                 write!(f, "{error}")
             }
             ValueParseError::InvalidParentheses => write!(f, "Invalid Parentheses"),
+            ValueParseError::UnknownFunction(name) => write!(f, "Unknown Function: {name}"),
+            ValueParseError::InvalidArguments(function) => write!(
+                f,
+                "Invalid Arguments: {} takes {}",
+                function.name(),
+                function.arguments()
+            ),
+            ValueParseError::InvalidBinding(statement) => {
+                write!(f, "Invalid Binding: {statement}")
+            }
+            ValueParseError::InvalidConditional(source) => {
+                write!(f, "Invalid Conditional: {source}")
+            }
+            ValueParseError::InvalidWeight(weight) => write!(f, "Invalid Weight: {weight}"),
+            ValueParseError::InvalidFaces(faces) => write!(f, "Invalid Faces: {faces}"),
+            ValueParseError::MissingThreshold(test) => write!(f, "Missing Threshold: {test}"),
         }
     }
 }
 
 impl Error for ValueParseError {}
 
-#[derive(Debug, Shoulda, Clone)]
+impl ValueParseError {
+    /// the byte range of `source` that caused the error, if it can be pinned down
+    pub fn span(&self, source: &str) -> Option<Range<usize>> {
+        match self {
+            ValueParseError::InvalidOperators(c) => c.first().and_then(|op| {
+                source
+                    .find(op.as_str())
+                    .map(|start| start..start + op.len())
+            }),
+            ValueParseError::InvalidParentheses => {
+                let mut open = Vec::new();
+                for (i, c) in source.char_indices() {
+                    match c {
+                        '(' => open.push(i),
+                        ')' if open.pop().is_none() => return Some(i..i + 1),
+                        _ => {}
+                    }
+                }
+                open.first().map(|i| *i..*i + 1)
+            }
+            ValueParseError::UnknownFunction(name) => source
+                .find(name.as_str())
+                .map(|start| start..start + name.len()),
+            ValueParseError::InvalidArguments(function) => source
+                .find(function.name())
+                .map(|start| start..start + function.name().len()),
+            ValueParseError::InvalidBinding(statement)
+            | ValueParseError::InvalidConditional(statement)
+            | ValueParseError::InvalidWeight(statement)
+            | ValueParseError::InvalidFaces(statement)
+            | ValueParseError::MissingThreshold(statement) => source
+                .find(statement.as_str())
+                .map(|start| start..start + statement.len()),
+        }
+    }
+}
+
+#[derive(Debug, Shoulda, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Value {
     Default,
     Constant(i32),
+    Variable(String),
     Operator(Rc<Operator>),
 }
 
-fn order_of_operations() -> &'static Vec<(&'static str, Regex)> {
-    const ORDER: [&str; 8] = ["+", "-", "*", "/", "cs<=", "cs<", "kh", "d"];
-    const ANYTHING_WITH_VALID_PARENTHESES: &str = r"([^\(\)]*(?:\((?:.|\s)*\))*[^\(\)]*)";
-    static LAZY: Lazy<Vec<(&'static str, Regex)>> = Lazy::new(|| {
-        ORDER
+/// operators from loosest to tightest binding, an expression is split at the loosest one first
+const ORDER: [&str; 16] = [
+    "|", "==", "!=", "<=", ">=", "<", ">", "vs", "+", "-", "*", "/", "cs<=", "cs<", "kh", "d",
+];
+
+/// unary `-` and `+` bind tighter than `/` and looser than `cs<`, the index in [`ORDER`] they are
+/// tried at. Counting successes like `10d10[>=8]` is tried right after them
+const UNARY: usize = 12;
+
+/// the name the condition of `x | c` refers to the outcome of `x` by, it can't be written as a
+/// variable so it never clashes with one
+pub const GIVEN: &str = "|";
+
+/// whether `s` ends in something an operator can apply to, which makes a `-` or `+` after it
+/// binary instead of a sign
+fn follows_operand(s: &str) -> bool {
+    s.trim_end()
+        .ends_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | ')' | ']'))
+}
+
+/// the byte index of the last `op` in `s` that isn't inside parentheses, brackets or a sign
+fn split_point(s: &str, op: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut found = None;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ if depth == 0
+                && s[i..].starts_with(op)
+                && (!matches!(op, "-" | "+") || follows_operand(&s[..i]))
+                // `vs` is a word, so it isn't part of a name next to it
+                && (op != "vs" || word_at(s, i, op))
+                // the `d` of a function name like `bands` isn't a roll
+                && (op != "d" || !in_function_name(s, i))
+                // the `<` of `cs<` and `cs<=` and the start of `<=` and `>=` aren't comparisons
+                && !(op.starts_with(['<', '>'])
+                    && (s[..i].ends_with("cs") || s[i + op.len()..].starts_with('=')))
+                // a comparison right after the `f` of a pool counts its failures
+                && !(Comparison::from_symbol(op).is_some() && after_failure(&s[..i])) =>
+            {
+                found = Some(i)
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// whether the byte index `i` of `s` is in the name of a function called right there
+fn in_function_name(s: &str, i: usize) -> bool {
+    let end = s[i..]
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .map_or(s.len(), |x| i + x);
+    s[end..].trim_start().starts_with('(')
+        && Function::ALL
             .iter()
-            .map(|x| (x, regex::escape(x)))
-            .map(|(x, y)| {
-                (
-                    x,
-                    format!(
-                        "^{ANYTHING_WITH_VALID_PARENTHESES}{y}{ANYTHING_WITH_VALID_PARENTHESES}$"
-                    ),
-                )
-            })
-            .map(|(x, y)| (*x, Regex::new(y.as_str()).unwrap()))
-            .collect()
-    });
-    LAZY.deref()
+            .any(|function| s[..end].ends_with(function.name()) && end - function.name().len() <= i)
+}
+
+/// whether `s` ends in a pool of dice like `10d10` or `4d6kh3`
+fn after_pool(s: &str) -> bool {
+    static POOL_REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?:^|[^a-zA-Z0-9_])\d*d\d+(?:kh\d+)?$").unwrap());
+    POOL_REGEX.is_match(s.trim_end())
+}
+
+/// whether `s` ends in the `f` of `10d10[>=7]f`, which the failures are compared with next
+fn after_failure(s: &str) -> bool {
+    static FAILURE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\]\s*f$").unwrap());
+    FAILURE_REGEX.is_match(s)
+}
+
+/// the byte index of the `f` in the test after a pool like `[>=7]f1` or `[8..10]f<3`, the faces
+/// after it are failures
+fn failure_point(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            'f' if depth == 0 && after_failure(&s[..=i]) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// the byte index of the last `[` in `s` that is written after a pool of dice and isn't inside
+/// parentheses or other brackets, like the one of `10d10[>=8]` which counts the dice that succeed
+fn success_point(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut found = None;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' if depth == 0 && after_pool(&s[..i]) => {
+                found = Some(i);
+                depth += 1;
+            }
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    found
+}
+
+/// the comparison `s` starts with and what it compares to, a number on its own is compared with
+/// `==` and so is one after `=`
+fn compared(s: &str) -> (Comparison, &str) {
+    ["==", "!=", "<=", ">=", "<", ">", "="]
+        .into_iter()
+        .find(|op| s.starts_with(op))
+        .map_or((Comparison::Equal, s), |op| {
+            let comparison = Comparison::from_symbol(op).unwrap_or(Comparison::Equal);
+            (comparison, &s[op.len()..])
+        })
+}
+
+/// the faces inside `[..]`, each one a number or an inclusive range like `8..10`
+fn faces(s: &str) -> Option<Vec<(i32, i32)>> {
+    let inner = s.strip_prefix('[')?.strip_suffix(']')?;
+    inner
+        .split(',')
+        .map(|part| {
+            let (low, high) = match part.split_once("..") {
+                Some((low, high)) => (low, high.strip_prefix('=').unwrap_or(high)),
+                None => (part, part),
+            };
+            let (low, high) = (low.trim().parse().ok()?, high.trim().parse().ok()?);
+            (low <= high).then_some((low, high))
+        })
+        .collect()
+}
+
+/// the byte index of the first `;` in `s` that isn't inside parentheses or brackets
+fn statement_end(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ';' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `value` with every part that is the same as `from` replaced by `to`, the condition of a
+/// nested `|` is left alone since it refers to its own outcome
+fn substitute(value: &Value, from: &Value, to: &Value) -> Value {
+    if value == from {
+        return to.clone();
+    }
+    let Value::Operator(op) = value else {
+        return value.clone();
+    };
+    let op = match op.as_ref() {
+        Operator::Given(x, condition) => {
+            Operator::Given(substitute(x, from, to), condition.clone())
+        }
+        op => op.map_operands(|x| substitute(x, from, to)),
+    };
+    Value::Operator(Rc::new(op))
+}
+
+/// whether `s` has the whole word `word` at byte index `i`
+fn word_at(s: &str, i: usize, word: &str) -> bool {
+    let ident = |c: char| c.is_alphanumeric() || c == '_';
+    s[i..].starts_with(word) && !s[..i].ends_with(ident) && !s[i + word.len()..].starts_with(ident)
+}
+
+/// splits the part of `if c then t else e` after the `if` into its three parts, `if`s nested
+/// in the branches are skipped over by matching them with their `else`
+fn conditional(s: &str) -> Option<(&str, &str, &str)> {
+    let mut depth = 0;
+    let mut nested = 0;
+    let mut then = None;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth != 0 => {}
+            _ if word_at(s, i, "if") => nested += 1,
+            _ if word_at(s, i, "then") && nested == 0 && then.is_none() => then = Some(i),
+            _ if word_at(s, i, "else") && nested > 0 => nested -= 1,
+            _ if word_at(s, i, "else") => {
+                let then = then?;
+                return Some((&s[..then], &s[then + 4..i], &s[i + 4..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// the comma separated parts of `s` that aren't inside parentheses
+fn split_arguments(s: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// whether `s` is wrapped in a single pair of matching parentheses
+fn enclosed(s: &str) -> bool {
+    if !s.starts_with('(') {
+        return false;
+    }
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return i == s.len() - 1;
+        }
+    }
+    false
 }
 
 impl FromStr for Value {
@@ -178,32 +1124,153 @@ impl FromStr for Value {
         if s.is_empty() {
             return Ok(Self::Default);
         }
-        static TRIM_PARENTHESIS_REGEX: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r"^\([^\(\)]*(?:\((?:.|\s)*\))*[^\(\)]*\)$").unwrap());
-        if TRIM_PARENTHESIS_REGEX.is_match(s) {
+        if enclosed(s) {
             let s = &s[1..(s.len() - 1)];
             return Self::from_str(s);
         }
         if let Ok(con) = s.parse::<i32>() {
             return Ok(Self::Constant(con));
         }
+        static VARIABLE_REGEX: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap());
+        static DICE_WORD_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:d|kh|\d)+$").unwrap());
+        if VARIABLE_REGEX.is_match(s) && !DICE_WORD_REGEX.is_match(s) {
+            return Ok(Self::Variable(s.to_string()));
+        }
+        if word_at(s, 0, "if") {
+            let (condition, then, otherwise) = conditional(&s[2..])
+                .ok_or_else(|| ValueParseError::InvalidConditional(s.to_string()))?;
+            return Ok(Self::Operator(Rc::new(Operator::If(
+                condition.parse()?,
+                then.parse()?,
+                otherwise.parse()?,
+            ))));
+        }
+        if let Some((keyword, rest)) = ["let", "roll"].into_iter().find_map(|keyword| {
+            s.strip_prefix(keyword)
+                .filter(|rest| rest.starts_with(char::is_whitespace))
+                .map(|rest| (keyword, rest))
+        }) {
+            let end = statement_end(rest).unwrap_or(rest.len());
+            let invalid = || ValueParseError::InvalidBinding(format!("{keyword}{}", &rest[..end]));
+            let (name, value) = rest[..end].split_once('=').ok_or_else(invalid)?;
+            let name = name.trim();
+            if !VARIABLE_REGEX.is_match(name)
+                || DICE_WORD_REGEX.is_match(name)
+                || matches!(name, "let" | "roll")
+                || end == rest.len()
+            {
+                return Err(invalid());
+            }
+            let (value, body) = (value.parse()?, rest[end + 1..].parse()?);
+            let name = name.to_string();
+            return Ok(Self::Operator(Rc::new(match keyword {
+                "let" => Operator::Let(name, value, body),
+                _ => Operator::Roll(name, value, body),
+            })));
+        }
 
-        for (op, regex) in order_of_operations() {
-            if let Some(caps) = regex.captures(s) {
-                let l = caps.get(1).unwrap().as_str();
-                let r = caps.get(2).unwrap().as_str();
-                let l = l.parse()?;
-                let r = r.parse()?;
+        for (n, op) in ORDER.into_iter().enumerate() {
+            if n == UNARY {
+                if let Some(operand) = s.strip_prefix('-') {
+                    return Ok(Self::Operator(Rc::new(Operator::Negate(operand.parse()?))));
+                }
+                if let Some(operand) = s.strip_prefix('+') {
+                    return operand.parse();
+                }
+                if let Some(i) = success_point(s) {
+                    let (pool, mut test) = (s[..i].parse()?, &s[i..]);
+                    let mut failure = None;
+                    if let Some(j) = failure_point(test) {
+                        let (comparison, threshold) = compared(test[j + 1..].trim_start());
+                        if threshold.trim().is_empty() {
+                            return Err(ValueParseError::MissingThreshold(test.trim().to_string()));
+                        }
+                        failure = Some((comparison, threshold.parse()?));
+                        test = &test[..j];
+                    }
+                    let test = test.trim();
+                    let inner = test
+                        .strip_prefix('[')
+                        .and_then(|x| x.strip_suffix(']'))
+                        .ok_or_else(|| ValueParseError::InvalidFaces(test.to_string()))?
+                        .trim();
+                    let successes = if inner.starts_with(['<', '>', '=', '!']) {
+                        let (comparison, threshold) = compared(inner);
+                        if threshold.trim().is_empty() {
+                            return Err(ValueParseError::MissingThreshold(test.to_string()));
+                        }
+                        Operator::Successes(comparison, pool, threshold.parse()?)
+                    } else {
+                        let faces = faces(test)
+                            .ok_or_else(|| ValueParseError::InvalidFaces(test.to_string()))?;
+                        Operator::Faces(pool, faces)
+                    };
+                    return Ok(Self::Operator(Rc::new(match failure {
+                        Some((comparison, threshold)) => Operator::Failures(
+                            Self::Operator(Rc::new(successes)),
+                            comparison,
+                            threshold,
+                        ),
+                        None => successes,
+                    })));
+                }
+            }
+            if let Some(i) = split_point(s, op) {
+                let l = s[..i].parse()?;
+                let r = s[i + op.len()..].parse()?;
+                // there is no roll to compare with on an empty side
+                if op == "vs" && (matches!(l, Value::Default) || matches!(r, Value::Default)) {
+                    return Err(ValueParseError::InvalidOperators(vec![op.to_string()]));
+                }
                 return Ok(Self::Operator(Rc::new(Operator::new(op, l, r).unwrap())));
             }
         }
+        static CALL_REGEX: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^([a-zA-Z_][a-zA-Z0-9_]*)\s*(\(.*\))$").unwrap());
+        if let Some(caps) = CALL_REGEX.captures(s) {
+            let (name, args) = (&caps[1], &caps[2]);
+            if enclosed(args) && name == "mix" {
+                let parts = split_arguments(&args[1..args.len() - 1])
+                    .into_iter()
+                    .map(|part| {
+                        let invalid = || ValueParseError::InvalidWeight(part.trim().to_string());
+                        let (weight, value) = part.split_once(':').ok_or_else(invalid)?;
+                        if value.trim().is_empty() {
+                            return Err(invalid());
+                        }
+                        Ok((weight.parse()?, value.parse()?))
+                    })
+                    .collect::<Result<Vec<(Weight, Value)>, _>>()?;
+                if parts.iter().all(|(weight, _)| weight.numerator == 0) {
+                    return Err(ValueParseError::InvalidWeight(args.to_string()));
+                }
+                return Ok(Self::Operator(Rc::new(Operator::Mix(parts))));
+            }
+            if enclosed(args) {
+                let function = Function::from_name(name)
+                    .ok_or_else(|| ValueParseError::UnknownFunction(name.to_string()))?;
+                let args = split_arguments(&args[1..args.len() - 1]);
+                if args.iter().any(|x| x.trim().is_empty()) {
+                    return Err(ValueParseError::InvalidArguments(function));
+                }
+                let args = args
+                    .into_iter()
+                    .map(str::parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if args.len() < function.min_arguments() || args.len() > function.max_arguments() {
+                    return Err(ValueParseError::InvalidArguments(function));
+                }
+                return Ok(Self::Operator(Rc::new(Operator::Call(function, args))));
+            }
+        }
         static VALID_CHAR: Lazy<Regex> = Lazy::new(|| {
             Regex::new(
                 format!(
                     "(?:{})",
-                    order_of_operations()
+                    ORDER
                         .iter()
-                        .map(|(x, _)| Cow::Borrowed(*x))
+                        .map(|x| Cow::Borrowed(*x))
                         .chain(('0'..='9').map(|x| Cow::Owned(x.to_string())))
                         .map(|x| regex::escape(x.deref()))
                         .collect::<Vec<_>>()
@@ -227,7 +1294,9 @@ impl FromStr for Value {
     }
 }
 
-#[derive(Debug, Shoulda, Clone)]
+#[derive(Debug, Shoulda, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Operator {
     Plus(Value, Value),
     Minus(Value, Value),
@@ -236,6 +1305,217 @@ pub enum Operator {
     KeepHighest(Value, Value),
     CountSuccesses(Value, Value),
     Dice(Value, Value),
+    Negate(Value),
+    Call(Function, Vec<Value>),
+    /// `let name = value; body`, every reference to `name` in `body` is rolled on its own
+    Let(String, Value, Value),
+    /// `roll name = value; body`, every reference to `name` in `body` is the same roll
+    Roll(String, Value, Value),
+    /// `l == r` and the like, 1 where the comparison holds and 0 where it doesn't
+    Compare(Comparison, Value, Value),
+    /// `if condition then value else value`, a condition holds when it isn't 0
+    If(Value, Value, Value),
+    /// `x | c`, the outcomes of `x` for which `c` holds, see [`Operator::given`]. A `roll` whose
+    /// body ends in one is conditioned along with it, so `roll r = d20; r | r + d4 >= 20` is how
+    /// likely each `r` is given the total
+    Given(Value, Value),
+    /// `attacker vs defender`, 1 where the attacker rolls higher and 0 where it doesn't, so ties go
+    /// to the defender
+    Versus(Value, Value),
+    /// `pool[>=n]` and the like written right after a pool of dice, the number of dice the
+    /// comparison holds for
+    Successes(Comparison, Value, Value),
+    /// `pool[8..10, 1]`, the number of dice that rolled one of the faces, the ranges include both
+    /// ends
+    Faces(Value, Vec<(i32, i32)>),
+    /// `pool[>=7]f1` and the like, the successes of a pool with every die that rolled a failure
+    /// taken away. A face that is both is worth nothing
+    Failures(Value, Comparison, Value),
+    /// `mix(w: x, ...)`, picks one of the values with a chance proportional to its weight. The
+    /// weights don't have to add up to 1, `mix(30%: d8, 30%: 2d6)` is as likely to roll either
+    Mix(Vec<(Weight, Value)>),
+}
+
+/// The exact weight of a part of `mix`, written as a whole number, a decimal, a fraction or a
+/// percentage and kept as a reduced fraction
+#[derive(Debug, Shoulda, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Weight {
+    pub numerator: BigInt,
+    pub denominator: BigInt,
+}
+
+impl Weight {
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Self {
+        let (mut a, mut b) = (numerator, denominator);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        let divisor = a.max(1);
+        Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        }
+    }
+}
+
+impl FromStr for Weight {
+    type Err = ValueParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        static WEIGHT_REGEX: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^(\d+)(?:\.(\d+))?(?:\s*/\s*(\d+))?\s*(%)?$").unwrap());
+        let invalid = || ValueParseError::InvalidWeight(s.trim().to_string());
+        let caps = WEIGHT_REGEX.captures(s.trim()).ok_or_else(invalid)?;
+        let decimals = caps.get(2).map_or("", |x| x.as_str());
+        let numerator = format!("{}{decimals}", &caps[1]).parse::<BigInt>().ok();
+        let mut denominator = 10u128.checked_pow(decimals.len() as u32);
+        if let Some(x) = caps.get(3) {
+            denominator = denominator
+                .zip(x.as_str().parse::<BigInt>().ok())
+                .and_then(|(d, x)| d.checked_mul(x));
+        }
+        if caps.get(4).is_some() {
+            denominator = denominator.and_then(|d| d.checked_mul(100));
+        }
+        match (numerator, denominator) {
+            (Some(n), Some(d)) if d != 0 => Ok(Weight::new(n, d)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Weight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.denominator {
+            1 => write!(f, "{}", self.numerator),
+            d => write!(f, "{}/{d}", self.numerator),
+        }
+    }
+}
+
+#[derive(Debug, Shoulda, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+}
+
+impl Comparison {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::LessEqual => "<=",
+            Comparison::GreaterEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::Greater => ">",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<=" => Comparison::LessEqual,
+            ">=" => Comparison::GreaterEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            _ => return None,
+        })
+    }
+
+    pub fn holds<T: PartialOrd>(&self, l: T, r: T) -> bool {
+        match self {
+            Comparison::Equal => l == r,
+            Comparison::NotEqual => l != r,
+            Comparison::LessEqual => l <= r,
+            Comparison::GreaterEqual => l >= r,
+            Comparison::Less => l < r,
+            Comparison::Greater => l > r,
+        }
+    }
+}
+
+/// The functions that can be called by name, every argument is rolled independently
+#[derive(Debug, Shoulda, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Function {
+    /// the lowest of any number of arguments
+    Min,
+    /// the highest of any number of arguments
+    Max,
+    Abs,
+    /// rounds a division down instead of towards zero
+    Floor,
+    /// rounds a division up instead of towards zero
+    Ceil,
+    /// `clamp(x, lo, hi)` keeps `x` between `lo` and `hi`
+    Clamp,
+    /// `bands(x, t1, t2, ...)` is how many of the thresholds `x` reaches, the band it falls in
+    Bands,
+}
+
+impl Function {
+    pub const ALL: [Function; 7] = [
+        Function::Min,
+        Function::Max,
+        Function::Abs,
+        Function::Floor,
+        Function::Ceil,
+        Function::Clamp,
+        Function::Bands,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Abs => "abs",
+            Function::Floor => "floor",
+            Function::Ceil => "ceil",
+            Function::Clamp => "clamp",
+            Function::Bands => "bands",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.name() == name)
+    }
+
+    pub fn min_arguments(&self) -> usize {
+        match self {
+            Function::Clamp => 3,
+            Function::Bands => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn max_arguments(&self) -> usize {
+        match self {
+            Function::Min | Function::Max | Function::Bands => usize::MAX,
+            Function::Abs | Function::Floor | Function::Ceil => 1,
+            Function::Clamp => 3,
+        }
+    }
+
+    /// how many arguments it takes, in words
+    fn arguments(&self) -> String {
+        match (self.min_arguments(), self.max_arguments()) {
+            (1, 1) => "1 argument".to_string(),
+            (min, usize::MAX) => {
+                format!("at least {min} argument{}", if min == 1 { "" } else { "s" })
+            }
+            (min, max) if min == max => format!("{min} arguments"),
+            (min, max) => format!("{min} to {max} arguments"),
+        }
+    }
 }
 
 impl Operator {
@@ -252,7 +1532,234 @@ impl Operator {
             "/" => Some(Self::Divide(l, r)),
             "-" => Some(Self::Minus(l, r)),
             "+" => Some(Self::Plus(l, r)),
-            _ => None,
+            "vs" => Some(Self::Versus(l, r)),
+            "|" => Some(Self::given(l, r)),
+            _ => Comparison::from_symbol(op).map(|x| Self::Compare(x, l, r)),
+        }
+    }
+
+    /// `x | condition`, where every part of `condition` that is written the same as `x` is the
+    /// same roll as `x`, so `2d6 | 2d6 >= 7` is a 2d6 that rolled at least 7. That holds for
+    /// every copy, `d6 | d6 + d6 > 6` is `d6 | d6 > 3`, dice that are meant to be rolled apart
+    /// have to be bound with `roll` first
+    pub fn given(x: Value, condition: Value) -> Self {
+        let condition = match x {
+            Value::Default | Value::Constant(_) => condition,
+            _ => substitute(&condition, &x, &Value::Variable(GIVEN.to_string())),
+        };
+        Self::Given(x, condition)
+    }
+
+    /// the same operator with `f` applied to every operand
+    fn map_operands(&self, mut f: impl FnMut(&Value) -> Value) -> Self {
+        match self {
+            Operator::Plus(l, r) => Operator::Plus(f(l), f(r)),
+            Operator::Minus(l, r) => Operator::Minus(f(l), f(r)),
+            Operator::Multiply(l, r) => Operator::Multiply(f(l), f(r)),
+            Operator::Divide(l, r) => Operator::Divide(f(l), f(r)),
+            Operator::KeepHighest(l, r) => Operator::KeepHighest(f(l), f(r)),
+            Operator::CountSuccesses(l, r) => Operator::CountSuccesses(f(l), f(r)),
+            Operator::Dice(l, r) => Operator::Dice(f(l), f(r)),
+            Operator::Negate(x) => Operator::Negate(f(x)),
+            Operator::Call(function, args) => {
+                Operator::Call(*function, args.iter().map(f).collect())
+            }
+            Operator::Let(name, value, body) => Operator::Let(name.clone(), f(value), f(body)),
+            Operator::Roll(name, value, body) => Operator::Roll(name.clone(), f(value), f(body)),
+            Operator::Compare(comparison, l, r) => Operator::Compare(*comparison, f(l), f(r)),
+            Operator::Versus(l, r) => Operator::Versus(f(l), f(r)),
+            Operator::Successes(comparison, l, r) => Operator::Successes(*comparison, f(l), f(r)),
+            Operator::Faces(pool, faces) => Operator::Faces(f(pool), faces.clone()),
+            Operator::Failures(successes, comparison, threshold) => {
+                Operator::Failures(f(successes), *comparison, f(threshold))
+            }
+            Operator::If(condition, then, otherwise) => {
+                Operator::If(f(condition), f(then), f(otherwise))
+            }
+            Operator::Given(x, condition) => Operator::Given(f(x), f(condition)),
+            Operator::Mix(parts) => Operator::Mix(
+                parts
+                    .iter()
+                    .map(|(weight, value)| (*weight, f(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<&Value> {
+        match self {
+            Operator::Plus(l, r)
+            | Operator::Minus(l, r)
+            | Operator::Multiply(l, r)
+            | Operator::Divide(l, r)
+            | Operator::KeepHighest(l, r)
+            | Operator::CountSuccesses(l, r)
+            | Operator::Dice(l, r)
+            | Operator::Compare(_, l, r)
+            | Operator::Versus(l, r)
+            | Operator::Successes(_, l, r)
+            | Operator::Failures(l, _, r)
+            | Operator::Given(l, r) => vec![l, r],
+            Operator::If(condition, then, otherwise) => vec![condition, then, otherwise],
+            Operator::Let(_, value, body) | Operator::Roll(_, value, body) => vec![value, body],
+            Operator::Negate(x) | Operator::Faces(x, _) => vec![x],
+            Operator::Call(_, args) => args.iter().collect(),
+            Operator::Mix(parts) => parts.iter().map(|(_, value)| value).collect(),
+        }
+    }
+}
+
+impl Operator {
+    /// the symbol the operator is written with and its index in the order of operations, a
+    /// lower index binds looser
+    fn symbol(&self) -> (&'static str, usize) {
+        let symbol = match self {
+            Operator::Plus(_, _) => "+",
+            Operator::Minus(_, _) | Operator::Negate(_) => "-",
+            Operator::Multiply(_, _) => "*",
+            Operator::Divide(_, _) => "/",
+            Operator::CountSuccesses(_, _) => "cs<",
+            Operator::KeepHighest(_, _) => "kh",
+            Operator::Dice(_, _) => "d",
+            Operator::Compare(comparison, _, _) => comparison.symbol(),
+            Operator::Versus(_, _) => "vs",
+            Operator::Successes(comparison, _, _) => comparison.symbol(),
+            Operator::Faces(_, _) => "[",
+            Operator::Failures(_, _, _) => "f",
+            Operator::Call(function, _) => function.name(),
+            Operator::Let(_, _, _) => "let",
+            Operator::Roll(_, _, _) => "roll",
+            Operator::If(_, _, _) => "if",
+            Operator::Given(_, _) => "|",
+            Operator::Mix(_) => "mix",
+        };
+        let binding = match self {
+            Operator::Negate(_)
+            | Operator::Successes(..)
+            | Operator::Faces(..)
+            | Operator::Failures(..) => UNARY,
+            Operator::Call(_, _) | Operator::Mix(_) => ORDER.len(),
+            _ => ORDER.iter().position(|x| *x == symbol).unwrap_or(0),
+        };
+        (symbol, binding)
+    }
+
+    /// whether the operator starts with a keyword and reaches to the end of the expression
+    fn binds(&self) -> bool {
+        matches!(
+            self,
+            Operator::Let(..) | Operator::Roll(..) | Operator::If(..)
+        )
+    }
+
+    /// `value` as an operand of this operator, wrapped in parentheses when it would otherwise be
+    /// parsed differently
+    fn operand(&self, value: &Value, right: bool) -> String {
+        let (symbol, binding) = self.symbol();
+        let parenthesize = match value {
+            Value::Default => false,
+            Value::Constant(c) => *c < 0,
+            // `xd6` would be read as a single name, and names can contain `d` and `kh`
+            Value::Variable(_) => matches!(symbol, "d" | "kh"),
+            // the body of a binding reaches to the end of the expression
+            Value::Operator(op) if op.binds() => true,
+            Value::Operator(op) => {
+                let (_, inner) = op.symbol();
+                // in `x - - 3` the second `-` would be read as a sign
+                inner < binding
+                    || (right && inner == binding)
+                    || (!right && !follows_operand(&value.to_string()))
+            }
+        };
+        if parenthesize {
+            format!("({value})")
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+impl Display for Operator {
+    /// the canonical form of the operator, with only the parentheses needed to parse back to the
+    /// same tree
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (symbol, _) = self.symbol();
+        if let Operator::Let(name, value, body) | Operator::Roll(name, value, body) = self {
+            let value = match value {
+                Value::Operator(op) if op.binds() => format!("({value})"),
+                _ => value.to_string(),
+            };
+            return write!(f, "{}", format!("{symbol} {name} = {value}; {body}").trim());
+        }
+        if let Operator::Given(x, condition) = self {
+            let condition = substitute(condition, &Value::Variable(GIVEN.to_string()), x);
+            let (x, condition) = (self.operand(x, false), self.operand(&condition, true));
+            return write!(f, "{}", format!("{x} | {condition}").trim());
+        }
+        if let Operator::If(condition, then, otherwise) = self {
+            let [condition, then] = [condition, then].map(|x| match x {
+                Value::Operator(op) if op.binds() => format!("({x})"),
+                _ => x.to_string(),
+            });
+            return write!(
+                f,
+                "{}",
+                format!("if {condition} then {then} else {otherwise}").trim()
+            );
+        }
+        if let Operator::Mix(parts) = self {
+            let parts = parts
+                .iter()
+                .map(|(weight, value)| format!("{weight}: {value}"))
+                .collect::<Vec<_>>();
+            return write!(f, "mix({})", parts.join(", "));
+        }
+        if let Operator::Faces(pool, faces) = self {
+            let faces = faces
+                .iter()
+                .map(|(low, high)| match low == high {
+                    true => low.to_string(),
+                    false => format!("{low}..{high}"),
+                })
+                .collect::<Vec<_>>();
+            return write!(f, "{}[{}]", self.operand(pool, false), faces.join(", "));
+        }
+        if let Operator::Failures(successes, comparison, threshold) = self {
+            let comparison = match comparison {
+                Comparison::Equal => "",
+                comparison => comparison.symbol(),
+            };
+            let threshold = self.operand(threshold, true);
+            return write!(f, "{successes}f{comparison}{threshold}");
+        }
+        if let Operator::Successes(_, pool, threshold) = self {
+            return write!(f, "{}[{symbol}{threshold}]", self.operand(pool, false));
+        }
+        if let Operator::Call(_, args) = self {
+            let args = args.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            return write!(f, "{symbol}({})", args.join(", "));
+        }
+        let [l, r] = match self.operands().as_slice() {
+            [l, r] => [self.operand(l, false), self.operand(r, true)],
+            // a bare constant after a sign would be read as a negative constant
+            [Value::Constant(c)] => return write!(f, "-({c})"),
+            [x] => return write!(f, "-{}", self.operand(x, false)),
+            _ => unreachable!("operators have one or two operands"),
+        };
+        match symbol {
+            "d" | "kh" => write!(f, "{l}{symbol}{r}"),
+            _ => write!(f, "{}", format!("{l} {symbol} {r}").trim()),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Default => Ok(()),
+            Value::Constant(c) => write!(f, "{c}"),
+            Value::Variable(name) => write!(f, "{name}"),
+            Value::Operator(op) => write!(f, "{op}"),
         }
     }
 }
@@ -0,0 +1,91 @@
+use std::str::FromStr;
+
+use model::distribution::Distribution;
+
+use crate::{
+    ast::{Value, ValueParseError},
+    eval::{Cache, Env, EvalError, EvalValue},
+};
+
+#[cfg(test)]
+mod tests {
+    use model::distribution::Distribution;
+    use shoulda::Shoulda;
+
+    use crate::eval::{EvalError, EvalValue};
+
+    use super::CompiledExpression;
+
+    #[test]
+    fn with_variable() {
+        let compiled: CompiledExpression = "d4 + bonus".parse().unwrap();
+        let dist = compiled
+            .with_variable("bonus", EvalValue::Constant(2))
+            .distribution()
+            .unwrap();
+        dist.should()
+            .eq(Distribution([(3, 1), (4, 1), (5, 1), (6, 1)].into()));
+    }
+
+    #[test]
+    fn variables_can_be_rebound() {
+        let compiled: CompiledExpression = "2d6 - bonus".parse().unwrap();
+        let plus_one = compiled.with_variable("bonus", EvalValue::Constant(1));
+        let plus_two = plus_one.with_variable("bonus", EvalValue::Constant(2));
+        plus_one.distribution().unwrap().min().should().eq(Some(1));
+        plus_two.distribution().unwrap().min().should().eq(Some(0));
+    }
+
+    #[test]
+    fn unknown_variable() {
+        let compiled: CompiledExpression = "d20 + bonus".parse().unwrap();
+        compiled
+            .distribution()
+            .should()
+            .eq(Err(EvalError::UnknownVariable("bonus".to_string())));
+    }
+}
+
+/// A parsed and optimized expression that remembers the result of every part that doesn't depend
+/// on a variable, so evaluating it again with other variables only redoes the work that changed
+#[derive(Debug, Clone)]
+pub struct CompiledExpression {
+    value: Value,
+    env: Env,
+}
+
+impl CompiledExpression {
+    pub fn new(value: Value) -> Self {
+        let value = value.optimize();
+        let env = Env::new().with_cache(Cache::new(&value));
+        Self { value, env }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// a copy of this expression with `name` bound to `value`, sharing the same cache
+    pub fn with_variable(&self, name: impl Into<String>, value: EvalValue) -> Self {
+        Self {
+            value: self.value.clone(),
+            env: self.env.clone().with(name, value),
+        }
+    }
+
+    pub fn eval(&self) -> Result<EvalValue, EvalError> {
+        self.value.eval_with(&self.env)
+    }
+
+    pub fn distribution(&self) -> Result<Distribution, EvalError> {
+        Ok(self.eval()?.into())
+    }
+}
+
+impl FromStr for CompiledExpression {
+    type Err = ValueParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s.parse()?))
+    }
+}
@@ -1,15 +1,378 @@
 use std::{
+    cell::RefCell,
+    cmp,
+    collections::{BTreeSet, HashMap, HashSet},
     error::Error,
     fmt::Display,
-    ops::{Add, Div, Mul, Sub},
+    rc::Rc,
 };
 
-use model::{dice::Dice, distribution::Distribution, posibility_space::PosibilitySpace, Int, BigInt};
+use model::{
+    dice::Dice, distribution::Distribution, joint_distribution::JointDistribution, pool::Scoring,
+    posibility_space::PosibilitySpace, BigInt, Int,
+};
 use shoulda::Shoulda;
 
-use crate::ast::{Operator, Value};
+use crate::ast::{Function, Operator, Value, GIVEN};
 
-#[derive(Debug, Shoulda)]
+#[cfg(test)]
+mod tests {
+    use model::{
+        dice::Dice, distribution::Distribution, pool::Scoring, posibility_space::PosibilitySpace,
+    };
+    use shoulda::Shoulda;
+
+    use crate::{
+        ast::Value,
+        eval::{Env, EvalError, EvalValue},
+    };
+
+    fn distribution(s: &str) -> Distribution {
+        s.parse::<Value>().unwrap().eval().unwrap().into()
+    }
+
+    #[test]
+    fn negate_dice() {
+        distribution("-d6").should().eq(Distribution(
+            [(-1, 1), (-2, 1), (-3, 1), (-4, 1), (-5, 1), (-6, 1)].into(),
+        ));
+    }
+
+    #[test]
+    fn negate_overflow() {
+        for expression in ["--2147483648", "-(-2147483648)", "-(d6 - 2147483647 - 2)"] {
+            let value: Value = expression.parse().unwrap();
+            value.eval().should().eq(Err(EvalError::Overflow));
+        }
+    }
+
+    #[test]
+    fn double_negation() {
+        let value: Value = "--3".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(3)));
+    }
+
+    #[test]
+    fn multiply_negated_dice() {
+        distribution("2*-d4")
+            .should()
+            .eq(Distribution([(-2, 1), (-4, 1), (-6, 1), (-8, 1)].into()));
+    }
+
+    #[test]
+    fn max_with_constant() {
+        distribution("max(1, 2d6-4)").should().eq(Distribution(
+            [
+                (1, 10),
+                (2, 5),
+                (3, 6),
+                (4, 5),
+                (5, 4),
+                (6, 3),
+                (7, 2),
+                (8, 1),
+            ]
+            .into(),
+        ));
+    }
+
+    #[test]
+    fn advantage() {
+        let dist = distribution("max(d20, d20)");
+        dist.total().should().eq(400);
+        dist.0[&20].should().eq(39);
+        dist.0[&1].should().eq(1);
+    }
+
+    #[test]
+    fn min_of_three() {
+        let dist = distribution("min(d6, d6, d6)");
+        dist.total().should().eq(216);
+        dist.0[&6].should().eq(1);
+        dist.0[&1].should().eq(91);
+    }
+
+    #[test]
+    fn abs_and_clamp() {
+        distribution("abs(d4 - 2)")
+            .should()
+            .eq(Distribution([(1, 2), (0, 1), (2, 1)].into()));
+        distribution("clamp(d6, 2, 5)")
+            .should()
+            .eq(Distribution([(2, 2), (3, 1), (4, 1), (5, 2)].into()));
+        let value: Value = "abs(-2147483648)".parse().unwrap();
+        value.eval().should().eq(Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn bands() {
+        distribution("bands(2d6, 7, 10)")
+            .should()
+            .eq(Distribution([(0, 15), (1, 15), (2, 6)].into()));
+        let value: Value = "bands(d6, d6)".parse().unwrap();
+        value.eval().should().eq(Err(EvalError::InvalidArgForBands));
+        // the degrees of success of a Pathfinder 2e check, a natural 20 or 1 moves it by one
+        let pf2e = distribution(
+            "roll n = d20; clamp(bands(n + 10, 11, 20, 30) + (n == 20) - (n == 1), 0, 3)",
+        );
+        pf2e.should()
+            .eq(Distribution([(0, 1), (1, 8), (2, 10), (3, 1)].into()));
+    }
+
+    #[test]
+    fn floor_and_ceil() {
+        let value: Value = "floor(-7 / 2)".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(-4)));
+        let value: Value = "ceil(7 / 2)".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(4)));
+        distribution("ceil(d6 / 2)")
+            .should()
+            .eq(Distribution([(1, 2), (2, 2), (3, 2)].into()));
+    }
+
+    #[test]
+    fn independent_binding() {
+        distribution("let x = d6; x + x")
+            .should()
+            .eq(distribution("2d6"));
+        distribution("let atk = d20 + 5; let dmg = 2d6 + 3; atk + dmg")
+            .should()
+            .eq(distribution("d20 + 2d6 + 8"));
+    }
+
+    #[test]
+    fn shared_binding() {
+        distribution("roll x = d6; x + x")
+            .should()
+            .eq(distribution("d6 * 2"));
+        distribution("roll x = d6; x - x")
+            .should()
+            .eq(Distribution([(0, 1)].into()));
+        distribution("roll x = d4; let y = x + d4; y - x")
+            .should()
+            .eq(distribution("d4"));
+    }
+
+    #[test]
+    fn shared_rolls_are_told_apart() {
+        distribution("roll x = d4; let y = x + d4; roll x = d6; y + x")
+            .should()
+            .eq(distribution("d4 + d4 + d6"));
+        distribution("roll x = d4; (roll x = d6; x * 10) + x")
+            .should()
+            .eq(distribution("d6 * 10 + d4"));
+    }
+
+    #[test]
+    fn shared_dice_count() {
+        let parts = (1..=4)
+            .map(|n| (1, distribution(&format!("{n}d6"))))
+            .collect::<Vec<_>>();
+        distribution("roll n = d4; n d6")
+            .should()
+            .eq(Distribution::mixture(&parts));
+    }
+
+    #[test]
+    fn joint() {
+        let value: Value =
+            "roll atk = d20; let bonus = 5; if atk + bonus >= 15 then 2d6 + 3 else 0"
+                .parse()
+                .unwrap();
+        let joint = value.joint_with(&Env::new()).unwrap();
+        let probabilities = |s: &str| distribution(s).probabilities();
+        joint
+            .condition(|x| x[0] >= 10)
+            .component(1)
+            .probabilities()
+            .should()
+            .eq(probabilities("2d6 + 3"));
+        joint
+            .condition(|x| x[1] == 0)
+            .component(0)
+            .probabilities()
+            .should()
+            .eq(probabilities("d9"));
+        joint
+            .project(|x| x[1])
+            .probabilities()
+            .should()
+            .eq(probabilities("if d20 >= 10 then 2d6 + 3 else 0"));
+    }
+
+    #[test]
+    fn given() {
+        distribution("(2d6 | 2d6 >= 7)").should().eq(Distribution(
+            [(7, 6), (8, 5), (9, 4), (10, 3), (11, 2), (12, 1)].into(),
+        ));
+        let likely_die = Distribution([(16, 1), (17, 2), (18, 3), (19, 4), (20, 4)].into());
+        distribution("d20 | d20 + d4 >= 20")
+            .should()
+            .eq(likely_die.clone());
+        distribution("roll r = d20; let total = r + d4; r | total >= 20")
+            .should()
+            .eq(likely_die);
+        // every `d6` in the condition is the same roll as `x`, a fresh die has to be a `roll`
+        distribution("d6 | d6 + d6 > 6")
+            .should()
+            .eq(distribution("d6 | d6 > 3"));
+        distribution("roll other = d6; d6 | d6 + other > 6")
+            .should()
+            .eq(Distribution(
+                [(1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (6, 6)].into(),
+            ));
+        let value: Value = "d6 | d6 > 6".parse().unwrap();
+        value
+            .eval()
+            .should()
+            .eq(Err(EvalError::ImpossibleCondition));
+    }
+
+    #[test]
+    fn successes() {
+        distribution("6d6[=6]")
+            .should()
+            .eq(distribution("6d6 cs< 5"));
+        distribution("4d6kh3[>=5]")
+            .should()
+            .eq(distribution("4d6kh3 cs< 4"));
+        distribution("5d10[8..10]")
+            .should()
+            .eq(distribution("5d10[>7]"));
+        distribution("6d6[1, 6]")
+            .should()
+            .eq(distribution("6d6[>4]"));
+        let dist = distribution("20d10[>=8]");
+        dist.total().should().eq(10u128.pow(20));
+        dist.0[&20].should().eq(3u128.pow(20));
+        distribution("4d6 cs<= 2")
+            .should()
+            .eq(distribution("4d6[>3]"));
+        distribution("2d6>=7")
+            .should()
+            .eq(Distribution([(0, 15), (1, 21)].into()));
+        distribution("if 2d6>=7 then 1 else 0")
+            .should()
+            .eq(Distribution([(0, 5), (1, 7)].into()));
+        distribution("5+d20>=15")
+            .should()
+            .eq(distribution("d20 + 5 >= 15"));
+    }
+
+    #[test]
+    fn failures() {
+        let dist = distribution("2d10[>=7]f1");
+        dist.total().should().eq(100);
+        dist.0[&-2].should().eq(1);
+        dist.0[&0].should().eq(25 + 2 * 4);
+        dist.0[&2].should().eq(16);
+        let d10 = Distribution::from(PosibilitySpace::from(Dice(10)));
+        let scoring = Scoring([(1, -1), (2, -1), (9, 1), (10, 1)].into());
+        distribution("6d10[9..10]f<=2")
+            .should()
+            .eq(d10.score_pool(6, &scoring).net);
+        distribution("3d10kh2[>=9]f<3")
+            .should()
+            .eq(PosibilitySpace::from(Dice(10))
+                .multiply(3)
+                .keep_highest(2)
+                .score(&scoring)
+                .net);
+        distribution("4d6[>=4]f>=4")
+            .should()
+            .eq(Distribution([(0, 1296)].into()));
+    }
+
+    #[test]
+    fn versus() {
+        distribution("d20 + 3 vs d20 + 5")
+            .should()
+            .eq(Distribution([(0, 247), (1, 153)].into()));
+        distribution("d4 vs 4")
+            .should()
+            .eq(Distribution([(0, 4)].into()));
+        distribution("2 vs 1")
+            .should()
+            .eq(Distribution([(1, 1)].into()));
+    }
+
+    #[test]
+    fn mix() {
+        distribution("mix(30%: d8, 0.7: 2d6)")
+            .should()
+            .eq(Distribution::weighted_mixture(&[
+                ((3, 10), distribution("d8")),
+                ((7, 10), distribution("2d6")),
+            ])
+            .unwrap());
+        distribution("mix(1/3: 0, 1/2: 1, 0: 1/0)")
+            .should()
+            .eq(Distribution([(0, 2), (1, 3)].into()));
+        distribution("mix(30%: d8, 30%: 2d6)")
+            .should()
+            .eq(distribution("mix(1: d8, 1: 2d6)"));
+        let value: Value = "mix(1/4294967291: 1, 1/4294967279: 2, 1/4294967231: 3, \
+                            1/4294967197: 4, 1/4294967189: 5)"
+            .parse()
+            .unwrap();
+        value.eval().should().eq(Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn binding_shadows() {
+        let value: Value = "let x = 1; (let x = 2; x) + x".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(3)));
+        let value: Value = "roll x = 5; x * x".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(25)));
+    }
+
+    #[test]
+    fn comparisons() {
+        distribution("d6 >= 5")
+            .should()
+            .eq(Distribution([(0, 4), (1, 2)].into()));
+        distribution("2d6 == 7")
+            .should()
+            .eq(Distribution([(0, 30), (1, 6)].into()));
+        let value: Value = "3 != 2 + 1".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(0)));
+    }
+
+    #[test]
+    fn conditional() {
+        let dist = distribution("if d20 + 5 >= 15 then 2d6 + 3 else 0");
+        dist.probability_at_least(1).should().eq(0.55);
+        dist.0[&0].should().eq(36 * 9);
+        dist.0[&15].should().eq(11);
+        distribution("if d4 > 0 then d4 else 1/0")
+            .should()
+            .eq(distribution("d4"));
+        let value: Value = "if 0 then 1/0 else 2".parse().unwrap();
+        value.eval().should().eq(Ok(EvalValue::Constant(2)));
+    }
+
+    #[test]
+    fn critical_hit() {
+        let dist = distribution(
+            "roll r = d20; if r == 20 then 2d6 * 2 else if r + 5 >= 15 then 2d6 else 0",
+        );
+        dist.total().should().eq(20 * 36);
+        dist.0[&0].should().eq(9 * 36);
+        dist.0[&24].should().eq(1);
+        dist.max().should().eq(Some(24));
+    }
+
+    #[test]
+    fn negate_parentheses() {
+        distribution("-(1d4+1)")
+            .should()
+            .eq(Distribution([(-2, 1), (-3, 1), (-4, 1), (-5, 1)].into()));
+    }
+}
+
+#[derive(Debug, Shoulda, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum EvalValue {
     Constant(i32),
     PreDice(PosibilitySpace),
@@ -31,9 +394,17 @@ pub enum EvalError {
     InvalidArgForDice,
     InvalidArgForKeepHeighest,
     InvalidArgForCountSuccesses,
+    InvalidArgForBands,
+    /// a call without the arguments its function needs
+    MissingArgument(Function),
+    /// the result doesn't fit in an `i32`
+    Overflow,
     MultiplyDiceWithDice,
     DivideDiceWithDice,
     DivideByZero,
+    UnknownVariable(String),
+    /// the condition of `x | c` can't hold
+    ImpossibleCondition,
 }
 
 impl Display for EvalError {
@@ -55,50 +426,586 @@ impl Display for EvalError {
             EvalError::InvalidArgForCountSuccesses => {
                 write!(f, "Eval Error: Invalid arg for count successes")
             }
+            EvalError::InvalidArgForBands => {
+                write!(
+                    f,
+                    "Eval Error: Invalid arg for bands, thresholds have to be constants"
+                )
+            }
+            EvalError::MissingArgument(function) => {
+                write!(f, "Eval Error: {} is missing an argument", function.name())
+            }
+            EvalError::Overflow => write!(f, "Eval Error: The result is too big"),
+            EvalError::UnknownVariable(name) => {
+                write!(f, "Eval Error: Unknown variable {name}")
+            }
+            EvalError::ImpossibleCondition => {
+                write!(f, "Eval Error: The condition can never hold")
+            }
         }
     }
 }
 
 impl Error for EvalError {}
 
+/// The variables an expression is evaluated with
+#[derive(Debug, Default, Clone)]
+pub struct Env {
+    variables: HashMap<String, EvalValue>,
+    cache: Option<Cache>,
+    rolls: Option<Rolls>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: EvalValue) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: EvalValue) {
+        self.variables.insert(name.into(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EvalValue> {
+        self.variables.get(name)
+    }
+
+    pub(crate) fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+}
+
+/// Results of operators that don't reference any variables, keyed by the address of the operator.
+/// Only valid for as long as the expression it was built from is alive, so it must never outlive it
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Cache {
+    cacheable: Rc<HashSet<*const Operator>>,
+    values: Rc<RefCell<HashMap<*const Operator, EvalValue>>>,
+}
+
+impl Cache {
+    pub(crate) fn new(value: &Value) -> Self {
+        fn collect(value: &Value, cacheable: &mut HashSet<*const Operator>) -> bool {
+            match value {
+                Value::Default | Value::Constant(_) => true,
+                Value::Variable(_) => false,
+                Value::Operator(o) => {
+                    let mut pure = true;
+                    for x in o.operands() {
+                        pure &= collect(x, cacheable);
+                    }
+                    if pure {
+                        cacheable.insert(Rc::as_ptr(o));
+                    }
+                    pure
+                }
+            }
+        }
+        let mut cacheable = HashSet::new();
+        collect(value, &mut cacheable);
+        Self {
+            cacheable: Rc::new(cacheable),
+            values: Default::default(),
+        }
+    }
+}
+
+/// The rolls shared with `roll` that are being enumerated. Every part of an expression is only
+/// evaluated once for each combination of values of the rolls it depends on, so a part that
+/// doesn't depend on a roll isn't redone for every outcome of it
+#[derive(Debug, Default, Clone)]
+struct Rolls {
+    /// the value each enclosing roll, keyed by the address of its operator, has right now
+    current: Vec<(*const Operator, Int)>,
+    /// the rolls each part depends on, directly or through a `let`
+    dependencies: Rc<HashMap<*const Operator, Vec<*const Operator>>>,
+    values: Rc<RefCell<HashMap<RollKey, EvalValue>>>,
+}
+
+/// a part of an expression and the values of the rolls it depends on
+type RollKey = (*const Operator, Vec<Int>);
+
+impl Rolls {
+    /// the dependencies of every part of `roll name = value; body`, rolls are told apart by their
+    /// operator instead of their name so a roll that is shadowed by another can't be mixed up
+    /// with it
+    fn new(roll: &Operator, name: &str, value: &Value, body: &Value) -> Self {
+        type Scope<'a> = HashMap<&'a str, BTreeSet<*const Operator>>;
+        fn collect<'a>(
+            value: &'a Value,
+            scope: &Scope<'a>,
+            dependencies: &mut HashMap<*const Operator, Vec<*const Operator>>,
+        ) -> BTreeSet<*const Operator> {
+            let Value::Operator(o) = value else {
+                return match value {
+                    Value::Variable(name) => scope.get(name.as_str()).cloned().unwrap_or_default(),
+                    _ => BTreeSet::new(),
+                };
+            };
+            let found = match o.as_ref() {
+                Operator::Let(_, value, body)
+                | Operator::Roll(_, value, body)
+                | Operator::Given(value, body) => {
+                    let mut found = collect(value, scope, dependencies);
+                    let (name, bound) = match o.as_ref() {
+                        Operator::Let(name, _, _) => (name.as_str(), found.clone()),
+                        Operator::Roll(name, _, _) => (name.as_str(), [Rc::as_ptr(o)].into()),
+                        _ => (GIVEN, [Rc::as_ptr(o)].into()),
+                    };
+                    let mut inner = scope.clone();
+                    inner.insert(name, bound);
+                    let mut body = collect(body, &inner, dependencies);
+                    body.remove(&Rc::as_ptr(o));
+                    found.append(&mut body);
+                    found
+                }
+                op => op
+                    .operands()
+                    .into_iter()
+                    .flat_map(|x| collect(x, scope, dependencies))
+                    .collect(),
+            };
+            dependencies.insert(Rc::as_ptr(o), found.iter().copied().collect());
+            found
+        }
+        let mut dependencies = HashMap::new();
+        collect(value, &Scope::new(), &mut dependencies);
+        let scope = [(name, [roll as *const Operator].into())].into();
+        collect(body, &scope, &mut dependencies);
+        Self {
+            current: Vec::new(),
+            dependencies: Rc::new(dependencies),
+            values: Default::default(),
+        }
+    }
+
+    /// the key `o` is remembered by, the values of the rolls it depends on
+    fn key(&self, o: &Rc<Operator>) -> Option<RollKey> {
+        let values = self
+            .dependencies
+            .get(&Rc::as_ptr(o))?
+            .iter()
+            .map(|roll| {
+                self.current
+                    .iter()
+                    .rev()
+                    .find(|(x, _)| x == roll)
+                    .map(|(_, value)| *value)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((Rc::as_ptr(o), values))
+    }
+}
+
 impl Operator {
     pub fn eval(self) -> Result<EvalValue, EvalError> {
+        self.eval_with(&Env::new())
+    }
+
+    pub fn eval_with(&self, env: &Env) -> Result<EvalValue, EvalError> {
+        match self {
+            Operator::Plus(l, r) => l.plus(r, env),
+            Operator::Minus(l, r) => l.minus(r, env),
+            Operator::Multiply(l, r) => l.multiply(r, env),
+            Operator::Divide(l, r) => l.divide(r, env),
+            Operator::KeepHighest(l, r) => l.keep_heighest(r, env),
+            Operator::CountSuccesses(l, r) => l.count_successes(r, env),
+            Operator::Successes(_, _, _) | Operator::Faces(_, _) => {
+                let (pool, success) = self.success_test(env)?;
+                pool.score_where(|x| success(x) as Int, env)
+            }
+            Operator::Failures(test, comparison, threshold) => {
+                let Value::Operator(test) = test else {
+                    return Err(EvalError::InvalidArgForCountSuccesses);
+                };
+                let (pool, success) = test.success_test(env)?;
+                let n = threshold.threshold(env)?;
+                pool.score_where(|x| success(x) as Int - comparison.holds(x, n) as Int, env)
+            }
+            Operator::Dice(l, r) => l.dice(r, env),
+            Operator::Negate(x) => x.negate(env),
+            Operator::Call(function, args) => function.eval_with(args, env),
+            Operator::Let(name, value, body) => {
+                body.eval_with(&env.clone().with(name, value.eval_with(env)?))
+            }
+            // the condition also applies to the roll, so every outcome of it is as likely as it
+            // is given the condition instead of as likely as it is on its own
+            Operator::Roll(_, _, body) if body.ends_in_condition() => {
+                let joint = held(self.joint_holds(env)?)?;
+                Ok(EvalValue::PostDice(joint.project(|x| x[x.len() - 1])))
+            }
+            Operator::Roll(name, value, body) => self.shared(name, value, body, env),
+            Operator::Compare(comparison, l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => {
+                    Ok(EvalValue::Constant(comparison.holds(l, r) as Int))
+                }
+                (l, r) => Ok(EvalValue::PostDice(
+                    Distribution::from(l).combine(&r.into(), |l, r| comparison.holds(l, r) as Int),
+                )),
+            },
+            Operator::If(condition, then, otherwise) => condition.branch(then, otherwise, env),
+            Operator::Versus(l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => {
+                    Ok(EvalValue::Constant((l > r) as Int))
+                }
+                (l, r) => Ok(EvalValue::PostDice(
+                    Distribution::from(l).contest(&r.into()).outcome(),
+                )),
+            },
+            Operator::Mix(parts) => {
+                let parts = parts
+                    .iter()
+                    .filter(|(weight, _)| weight.numerator != 0)
+                    .map(|(weight, value)| {
+                        let dist = Distribution::from(value.eval_with(env)?);
+                        Ok(((weight.numerator, weight.denominator), dist))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Distribution::weighted_mixture(&parts)
+                    .map(EvalValue::PostDice)
+                    .ok_or(EvalError::Overflow)
+            }
+            Operator::Given(_, _) => Ok(EvalValue::PostDice(
+                held(self.joint_holds(env)?)?.component(0),
+            )),
+        }
+    }
+}
+
+/// a pool of dice and whether each of its faces is a success
+type SuccessTest<'a> = (&'a Value, Box<dyn Fn(Int) -> bool + 'a>);
+
+impl Operator {
+    /// the pool of `pool[>=n]` or `pool[8..10]` and which of its faces are successes
+    fn success_test(&self, env: &Env) -> Result<SuccessTest<'_>, EvalError> {
+        match self {
+            Operator::Successes(comparison, pool, threshold) => {
+                let n = threshold.threshold(env)?;
+                Ok((pool, Box::new(move |x| comparison.holds(x, n))))
+            }
+            Operator::Faces(pool, faces) => Ok((
+                pool,
+                Box::new(|x| faces.iter().any(|(low, high)| (*low..=*high).contains(&x))),
+            )),
+            _ => Err(EvalError::InvalidArgForCountSuccesses),
+        }
+    }
+
+    /// `f` applied to the environment of `body` for every outcome of `value`, which `self` is the
+    /// `roll` of, with `name` bound to it and how often it happens. The parts of `body` that
+    /// depend on the roll are remembered by `self`
+    fn outcomes<T>(
+        &self,
+        name: &str,
+        value: &Value,
+        body: &Value,
+        env: &Env,
+        f: impl Fn(&Env) -> Result<T, EvalError>,
+    ) -> Result<Vec<(Int, BigInt, T)>, EvalError> {
+        let dist = Distribution::from(value.eval_with(env)?);
+        let rolls = env
+            .rolls
+            .clone()
+            .unwrap_or_else(|| Rolls::new(self, name, value, body));
+        dist.sorted()
+            .into_iter()
+            .map(|(x, amount)| {
+                let mut env = env.clone().with(name, EvalValue::Constant(x));
+                let mut rolls = rolls.clone();
+                rolls.current.push((self, x));
+                env.rolls = Some(rolls);
+                Ok((x, amount, f(&env)?))
+            })
+            .collect()
+    }
+
+    /// `body` with `name` bound to a single roll of `value`, found by evaluating `body` for every
+    /// outcome and weighing the results by how likely that outcome is
+    fn shared(
+        &self,
+        name: &str,
+        value: &Value,
+        body: &Value,
+        env: &Env,
+    ) -> Result<EvalValue, EvalError> {
+        let mut parts = self.outcomes(name, value, body, env, |env| body.eval_with(env))?;
+        if let [(_, 1, _)] = parts.as_slice() {
+            return Ok(parts.remove(0).2);
+        }
+        let parts = parts
+            .into_iter()
+            .map(|(_, amount, x)| (amount, x.into()))
+            .collect::<Vec<_>>();
+        Ok(EvalValue::PostDice(Distribution::mixture(&parts)))
+    }
+
+    /// the joint distribution of the rolls `self` starts by sharing, its result and whether the
+    /// condition it ends in holds, which is always the case when it doesn't end in one
+    fn joint_holds(&self, env: &Env) -> Result<JointDistribution, EvalError> {
         match self {
-            Operator::Plus(l, r) => l + r,
-            Operator::Minus(l, r) => l - r,
-            Operator::Multiply(l, r) => l * r,
-            Operator::Divide(l, r) => l / r,
-            Operator::KeepHighest(l, r) => l.keep_heighest(r),
-            Operator::CountSuccesses(l, r) => todo!(),
-            Operator::Dice(l, r) => l.dice(r),
+            Operator::Let(name, value, body) => {
+                body.joint_holds(&env.clone().with(name, value.eval_with(env)?))
+            }
+            Operator::Roll(name, value, body) => {
+                let parts = self
+                    .outcomes(name, value, body, env, |env| body.joint_holds(env))?
+                    .into_iter()
+                    .map(|(x, amount, joint)| {
+                        let outcome = JointDistribution([(vec![x], 1)].into());
+                        (amount, outcome.product(&joint))
+                    })
+                    .collect::<Vec<_>>();
+                Ok(JointDistribution::mixture(&parts))
+            }
+            Operator::Given(x, condition) => {
+                let parts = self
+                    .outcomes(GIVEN, x, condition, env, |env| condition.eval_with(env))?
+                    .into_iter()
+                    .map(|(x, amount, holds)| {
+                        let holds = Distribution::from(holds).mutate(|c| (c != 0) as Int);
+                        let outcome = JointDistribution([(vec![x], 1)].into());
+                        (amount, outcome.product(&holds.into()))
+                    })
+                    .collect::<Vec<_>>();
+                Ok(JointDistribution::mixture(&parts))
+            }
+            _ => Ok(always(self.eval_with(env)?)),
         }
     }
 }
 
+/// `value` as a joint distribution with a condition that always holds
+fn always(value: EvalValue) -> JointDistribution {
+    JointDistribution::from(Distribution::from(value))
+        .product(&JointDistribution([(vec![1], 1)].into()))
+}
+
+/// the outcomes of a [`Operator::joint_holds`] where the condition holds, without the condition
+fn held(joint: JointDistribution) -> Result<JointDistribution, EvalError> {
+    let joint = joint.condition(|x| x.last() != Some(&0));
+    let len = joint
+        .0
+        .keys()
+        .next()
+        .ok_or(EvalError::ImpossibleCondition)?
+        .len();
+    Ok(joint.marginalize(&(0..len - 1).collect::<Vec<_>>()))
+}
+
+/// `l / r` rounded down when `up` isn't set and up when it is, instead of towards zero
+fn round_division(l: Int, r: Int, up: bool) -> Int {
+    let quotient = l / r;
+    if l % r == 0 {
+        quotient
+    } else if up == ((l < 0) == (r < 0)) {
+        quotient + if up { 1 } else { -1 }
+    } else {
+        quotient
+    }
+}
+
+impl Function {
+    pub fn eval_with(&self, args: &[Value], env: &Env) -> Result<EvalValue, EvalError> {
+        if let (Function::Floor | Function::Ceil, [Value::Operator(op)]) = (self, args) {
+            if let Operator::Divide(l, r) = op.as_ref() {
+                return self.round(l, r, env);
+            }
+        }
+        let args = args
+            .iter()
+            .map(|x| x.eval_with(env))
+            .collect::<Result<Vec<_>, _>>()?;
+        let constant = args.iter().all(|x| matches!(x, EvalValue::Constant(_)));
+        let mut args = args.into_iter().map(Distribution::from);
+        let first = args.next().ok_or(EvalError::MissingArgument(*self))?;
+        let dist = match self {
+            Function::Min => args.fold(first, |acc, x| acc.combine(&x, cmp::min)),
+            Function::Max => args.fold(first, |acc, x| acc.combine(&x, cmp::max)),
+            Function::Abs => {
+                if first.0.keys().any(|x| x.checked_abs().is_none()) {
+                    return Err(EvalError::Overflow);
+                }
+                first.mutate(|x| x.abs())
+            }
+            Function::Floor | Function::Ceil => first,
+            Function::Clamp => {
+                let lo = args.next().unwrap_or_else(|| first.clone());
+                let hi = args.next().unwrap_or_else(|| first.clone());
+                first.combine(&lo, cmp::max).combine(&hi, cmp::min)
+            }
+            Function::Bands => {
+                let thresholds = args
+                    .map(|x| match (x.min(), x.max()) {
+                        (Some(lo), Some(hi)) if lo == hi => Ok(lo),
+                        _ => Err(EvalError::InvalidArgForBands),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                first.mutate(|x| thresholds.iter().filter(|t| **t <= x).count() as Int)
+            }
+        };
+        Ok(match (constant, dist.min()) {
+            (true, Some(c)) => EvalValue::Constant(c),
+            _ => EvalValue::PostDice(dist),
+        })
+    }
+
+    /// `floor` or `ceil` of `l / r`, where an empty side is 1 like it is for `/`
+    fn round(&self, l: &Value, r: &Value, env: &Env) -> Result<EvalValue, EvalError> {
+        let side = |x: &Value| match x {
+            Value::Default => Ok(EvalValue::Constant(1)),
+            x => x.eval_with(env),
+        };
+        let (l, r) = (side(l)?, side(r)?);
+        let up = matches!(self, Function::Ceil);
+        if let (EvalValue::Constant(l), EvalValue::Constant(r)) = (&l, &r) {
+            return if *r == 0 {
+                Err(EvalError::DivideByZero)
+            } else {
+                Ok(EvalValue::Constant(round_division(*l, *r, up)))
+            };
+        }
+        let (l, r) = (Distribution::from(l), Distribution::from(r));
+        if r.0.contains_key(&0) {
+            return Err(EvalError::DivideByZero);
+        }
+        Ok(EvalValue::PostDice(
+            l.combine(&r, |l, r| round_division(l, r, up)),
+        ))
+    }
+}
+
 impl Value {
     pub fn eval(self) -> Result<EvalValue, EvalError> {
+        self.eval_with(&Env::new())
+    }
+
+    pub fn eval_with(&self, env: &Env) -> Result<EvalValue, EvalError> {
         match self {
             Value::Default => Ok(EvalValue::Constant(0)),
-            Value::Constant(c) => Ok(EvalValue::Constant(c)),
-            Value::Operator(o) => o.as_ref().clone().eval(),
+            Value::Constant(c) => Ok(EvalValue::Constant(*c)),
+            Value::Variable(name) => env
+                .get(name)
+                .cloned()
+                .ok_or_else(|| EvalError::UnknownVariable(name.clone())),
+            Value::Operator(o) => match (&env.cache, &env.rolls) {
+                (Some(cache), _) if cache.cacheable.contains(&Rc::as_ptr(o)) => {
+                    let key = Rc::as_ptr(o);
+                    if let Some(value) = cache.values.borrow().get(&key) {
+                        return Ok(value.clone());
+                    }
+                    let value = o.eval_with(env)?;
+                    cache.values.borrow_mut().insert(key, value.clone());
+                    Ok(value)
+                }
+                (_, Some(rolls)) => match rolls.key(o) {
+                    Some(key) => {
+                        if let Some(value) = rolls.values.borrow().get(&key) {
+                            return Ok(value.clone());
+                        }
+                        let value = o.eval_with(env)?;
+                        rolls.values.borrow_mut().insert(key, value.clone());
+                        Ok(value)
+                    }
+                    None => o.eval_with(env),
+                },
+                _ => o.eval_with(env),
+            },
         }
     }
 
-    pub fn count_successes(self, rhs: Self) -> Result<EvalValue, EvalError> {
+    /// the joint distribution of the rolls `self` starts by sharing, in the order they are rolled,
+    /// and its result as the last component, a `let` before or between them is skipped over. When
+    /// `self` ends in `x | c` only the outcomes where `c` holds are kept
+    pub fn joint_with(&self, env: &Env) -> Result<JointDistribution, EvalError> {
+        held(self.joint_holds(env)?)
+    }
+
+    fn joint_holds(&self, env: &Env) -> Result<JointDistribution, EvalError> {
+        match self {
+            Value::Operator(o) => o.joint_holds(env),
+            _ => Ok(always(self.eval_with(env)?)),
+        }
+    }
+
+    /// whether `self` is `x | c`, or a `let` or `roll` whose body ends in one
+    fn ends_in_condition(&self) -> bool {
+        let Value::Operator(o) = self else {
+            return false;
+        };
+        match o.as_ref() {
+            Operator::Let(_, _, body) | Operator::Roll(_, _, body) => body.ends_in_condition(),
+            Operator::Given(_, _) => true,
+            _ => false,
+        }
+    }
+
+    /// `then` where `self` isn't 0 and `otherwise` where it is, a branch that can't happen is
+    /// never evaluated
+    pub fn branch(
+        &self,
+        then: &Value,
+        otherwise: &Value,
+        env: &Env,
+    ) -> Result<EvalValue, EvalError> {
+        let dist = match self.eval_with(env)? {
+            EvalValue::Constant(0) => return otherwise.eval_with(env),
+            EvalValue::Constant(_) => return then.eval_with(env),
+            x => Distribution::from(x),
+        };
+        let holds: BigInt = dist
+            .0
+            .iter()
+            .filter(|(x, _)| **x != 0)
+            .map(|(_, n)| n)
+            .sum();
+        let fails = dist.total() - holds;
+        let mut parts = Vec::new();
+        for (weight, branch) in [(holds, then), (fails, otherwise)] {
+            if weight != 0 {
+                parts.push((weight, branch.eval_with(env)?.into()));
+            }
+        }
+        Ok(EvalValue::PostDice(Distribution::mixture(&parts)))
+    }
+
+    pub fn negate(&self, env: &Env) -> Result<EvalValue, EvalError> {
+        let negated = |d: Distribution| {
+            if d.0.keys().any(|x| x.checked_neg().is_none()) {
+                return Err(EvalError::Overflow);
+            }
+            Ok(EvalValue::PostDice(d.mutate(|x| -x)))
+        };
+        match self.eval_with(env)? {
+            EvalValue::Constant(c) => c
+                .checked_neg()
+                .map(EvalValue::Constant)
+                .ok_or(EvalError::Overflow),
+            EvalValue::PreDice(d) => negated(Distribution::from(d)),
+            EvalValue::PostDice(d) => negated(d),
+        }
+    }
+
+    pub fn count_successes(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
             (Value::Default, Value::Default) => Ok(EvalValue::PreDice(
                 PosibilitySpace::from(Dice(10)).count_successes(6),
             )),
             (Value::Default, Value::Constant(c)) => {
-                if c < 1 {
+                if *c < 1 {
                     Err(EvalError::InvalidArgForCountSuccesses)
                 } else {
                     Ok(EvalValue::PreDice(
-                        PosibilitySpace::from(Dice(10)).count_successes(c as usize),
+                        PosibilitySpace::from(Dice(10)).count_successes(*c as usize),
                     ))
                 }
             }
-            (Value::Default, Value::Operator(o)) => match o.as_ref().clone().eval()? {
+            (Value::Constant(_), _) => Err(EvalError::InvalidArgForCountSuccesses),
+            (Value::Default, o) => match o.eval_with(env)? {
                 EvalValue::Constant(c) => {
                     if c < 1 {
                         Err(EvalError::InvalidArgForCountSuccesses)
@@ -110,55 +1017,87 @@ impl Value {
                 }
                 _ => Err(EvalError::InvalidArgForCountSuccesses),
             },
-            (Value::Operator(o), Value::Default) => match o.as_ref().clone().eval()? {
+            (o, Value::Default) => match o.eval_with(env)? {
                 EvalValue::PreDice(d) => Ok(EvalValue::PreDice(d.count_successes(6))),
                 _ => Err(EvalError::InvalidArgForCountSuccesses),
             },
-            (Value::Operator(o), Value::Constant(c)) => {
-                if c < 1 {
+            (o, Value::Constant(c)) => {
+                if *c < 1 {
                     Err(EvalError::InvalidArgForCountSuccesses)
                 } else {
-                    match o.as_ref().clone().eval()? {
+                    match o.eval_with(env)? {
                         EvalValue::PreDice(d) => {
-                            Ok(EvalValue::PreDice(d.count_successes(c as usize)))
+                            Ok(EvalValue::PreDice(d.count_successes(*c as usize)))
                         }
                         _ => Err(EvalError::InvalidArgForCountSuccesses),
                     }
                 }
             }
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::PreDice(d), EvalValue::Constant(c)) => {
-                        if c < 1 {
-                            Err(EvalError::InvalidArgForCountSuccesses)
-                        } else {
-                            Ok(EvalValue::PreDice(d.count_successes(c as usize)))
-                        }
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::PreDice(d), EvalValue::Constant(c)) => {
+                    if c < 1 {
+                        Err(EvalError::InvalidArgForCountSuccesses)
+                    } else {
+                        Ok(EvalValue::PreDice(d.count_successes(c as usize)))
                     }
-                    _ => Err(EvalError::InvalidArgForCountSuccesses),
                 }
+                _ => Err(EvalError::InvalidArgForCountSuccesses),
+            },
+        }
+    }
+
+    /// what the dice in the pool are worth together when every face is worth `f` of it, a pool of
+    /// the same dice is scored one die at a time instead of rolling every combination of them
+    pub fn score_where<T: Fn(Int) -> Int>(&self, f: T, env: &Env) -> Result<EvalValue, EvalError> {
+        if let Value::Operator(op) = self {
+            if let Operator::Dice(amount, sides) = op.as_ref() {
+                let amount = match amount {
+                    Value::Default => 1,
+                    amount => match amount.eval_with(env)? {
+                        EvalValue::Constant(c) if c >= 1 => c as usize,
+                        _ => return Err(EvalError::InvalidArgForDice),
+                    },
+                };
+                let die = Distribution::from(Value::Default.dice(sides, env)?);
+                let scoring = Scoring(die.0.keys().map(|x| (*x, f(*x))).collect());
+                return Ok(EvalValue::PostDice(die.score_pool(amount, &scoring).net));
             }
+        }
+        match self.eval_with(env)? {
+            EvalValue::PreDice(d) => {
+                let faces = d.0.keys().flatten().map(|x| (*x, f(*x)));
+                Ok(EvalValue::PostDice(d.score(&Scoring(faces.collect())).net))
+            }
+            _ => Err(EvalError::InvalidArgForCountSuccesses),
+        }
+    }
+
+    /// the number a success or failure is compared to, which can't be rolled
+    fn threshold(&self, env: &Env) -> Result<Int, EvalError> {
+        match self.eval_with(env)? {
+            EvalValue::Constant(n) => Ok(n),
             _ => Err(EvalError::InvalidArgForCountSuccesses),
         }
     }
 
-    pub fn keep_heighest(self, rhs: Self) -> Result<EvalValue, EvalError> {
+    pub fn keep_heighest(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
             (Value::Default, Value::Default) => Ok(EvalValue::PreDice(
                 PosibilitySpace::from(Dice(20)).multiply(2).keep_highest(1),
             )),
             (Value::Default, Value::Constant(c)) => {
-                if c < 1 {
+                if *c < 1 {
                     Err(EvalError::InvalidArgForKeepHeighest)
                 } else {
                     Ok(EvalValue::PreDice(
                         PosibilitySpace::from(Dice(20))
                             .multiply(2)
-                            .keep_highest(c as usize),
+                            .keep_highest(*c as usize),
                     ))
                 }
             }
-            (Value::Default, Value::Operator(o)) => match o.as_ref().clone().eval()? {
+            (Value::Constant(_), _) => Err(EvalError::InvalidArgForKeepHeighest),
+            (Value::Default, o) => match o.eval_with(env)? {
                 EvalValue::Constant(c) => {
                     if c < 1 {
                         Err(EvalError::InvalidArgForKeepHeighest)
@@ -172,88 +1111,86 @@ impl Value {
                 }
                 _ => Err(EvalError::InvalidArgForKeepHeighest),
             },
-            (Value::Operator(o), Value::Default) => match o.as_ref().clone().eval()? {
+            (o, Value::Default) => match o.eval_with(env)? {
                 EvalValue::PreDice(d) => Ok(EvalValue::PreDice(d.keep_highest(1))),
                 _ => Err(EvalError::InvalidArgForKeepHeighest),
             },
-            (Value::Operator(o), Value::Constant(c)) => {
-                if c < 1 {
+            (o, Value::Constant(c)) => {
+                if *c < 1 {
                     Err(EvalError::InvalidArgForKeepHeighest)
                 } else {
-                    match o.as_ref().clone().eval()? {
-                        EvalValue::PreDice(d) => Ok(EvalValue::PreDice(d.keep_highest(c as usize))),
+                    match o.eval_with(env)? {
+                        EvalValue::PreDice(d) => {
+                            Ok(EvalValue::PreDice(d.keep_highest(*c as usize)))
+                        }
                         _ => Err(EvalError::InvalidArgForKeepHeighest),
                     }
                 }
             }
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::PreDice(d), EvalValue::Constant(c)) => {
-                        if c < 1 {
-                            Err(EvalError::InvalidArgForKeepHeighest)
-                        } else {
-                            Ok(EvalValue::PreDice(d.keep_highest(c as usize)))
-                        }
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::PreDice(d), EvalValue::Constant(c)) => {
+                    if c < 1 {
+                        Err(EvalError::InvalidArgForKeepHeighest)
+                    } else {
+                        Ok(EvalValue::PreDice(d.keep_highest(c as usize)))
                     }
-                    _ => Err(EvalError::InvalidArgForKeepHeighest),
                 }
-            }
-            _ => Err(EvalError::InvalidArgForKeepHeighest),
+                _ => Err(EvalError::InvalidArgForKeepHeighest),
+            },
         }
     }
 
-    pub fn dice(self, rhs: Self) -> Result<EvalValue, EvalError> {
+    pub fn dice(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
             (Value::Default, Value::Default) => Ok(EvalValue::PreDice(Dice(6).into())),
             (Value::Default, Value::Constant(c)) => {
-                if c < 1 {
+                if *c < 1 {
                     Err(EvalError::InvalidArgForDice)
                 } else {
-                    Ok(EvalValue::PreDice(Dice(c as Int).into()))
+                    Ok(EvalValue::PreDice(Dice(*c as Int).into()))
                 }
             }
-            (Value::Default, Value::Operator(o)) => match o.as_ref().clone().eval()? {
-                EvalValue::Constant(c) => {
-                    if c < 1 {
-                        Err(EvalError::InvalidArgForDice)
-                    } else {
-                        Ok(EvalValue::PreDice(Dice(c as Int).into()))
-                    }
-                }
-                _ => Err(EvalError::InvalidArgForDice),
-            },
             (Value::Constant(c), Value::Default) => {
-                if c < 1 {
+                if *c < 1 {
                     Err(EvalError::InvalidArgForDice)
                 } else {
                     Ok(EvalValue::PreDice(
-                        PosibilitySpace::from(Dice(6)).multiply(c as BigInt),
+                        PosibilitySpace::from(Dice(6)).multiply(*c as BigInt),
                     ))
                 }
             }
             (Value::Constant(l), Value::Constant(r)) => {
-                if l < 1 || r < 1 {
+                if *l < 1 || *r < 1 {
                     Err(EvalError::InvalidArgForDice)
                 } else {
                     Ok(EvalValue::PreDice(
-                        PosibilitySpace::from(Dice(r as Int)).multiply(l as BigInt),
+                        PosibilitySpace::from(Dice(*r as Int)).multiply(*l as BigInt),
                     ))
                 }
             }
-            (Value::Constant(c), Value::Operator(o)) => match o.as_ref().clone().eval()? {
+            (Value::Default, o) => match o.eval_with(env)? {
+                EvalValue::Constant(c) => {
+                    if c < 1 {
+                        Err(EvalError::InvalidArgForDice)
+                    } else {
+                        Ok(EvalValue::PreDice(Dice(c as Int).into()))
+                    }
+                }
+                _ => Err(EvalError::InvalidArgForDice),
+            },
+            (Value::Constant(c), o) => match o.eval_with(env)? {
                 EvalValue::Constant(x) => {
-                    if c < 1 || x < 1 {
+                    if *c < 1 || x < 1 {
                         Err(EvalError::InvalidArgForDice)
                     } else {
                         Ok(EvalValue::PreDice(
-                            PosibilitySpace::from(Dice(x as Int)).multiply(c as BigInt),
+                            PosibilitySpace::from(Dice(x as Int)).multiply(*c as BigInt),
                         ))
                     }
                 }
                 _ => Err(EvalError::InvalidArgForDice),
             },
-
-            (Value::Operator(o), Value::Default) => match o.as_ref().clone().eval()? {
+            (o, Value::Default) => match o.eval_with(env)? {
                 EvalValue::Constant(c) => {
                     if c < 1 {
                         Err(EvalError::InvalidArgForDice)
@@ -265,182 +1202,173 @@ impl Value {
                 }
                 _ => Err(EvalError::InvalidArgForDice),
             },
-            (Value::Operator(o), Value::Constant(c)) => match o.as_ref().clone().eval()? {
+            (o, Value::Constant(c)) => match o.eval_with(env)? {
                 EvalValue::Constant(x) => {
-                    if x < 1 || c < 1 {
+                    if x < 1 || *c < 1 {
                         Err(EvalError::InvalidArgForDice)
                     } else {
                         Ok(EvalValue::PreDice(
-                            PosibilitySpace::from(Dice(c as Int)).multiply(x as BigInt),
+                            PosibilitySpace::from(Dice(*c as Int)).multiply(x as BigInt),
                         ))
                     }
                 }
                 _ => Err(EvalError::InvalidArgForDice),
             },
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::Constant(l), EvalValue::Constant(r)) => {
-                        if l < 1 || r < 1 {
-                            Err(EvalError::InvalidArgForDice)
-                        } else {
-                            Ok(EvalValue::PreDice(
-                                PosibilitySpace::from(Dice(r as Int)).multiply(l as BigInt),
-                            ))
-                        }
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => {
+                    if l < 1 || r < 1 {
+                        Err(EvalError::InvalidArgForDice)
+                    } else {
+                        Ok(EvalValue::PreDice(
+                            PosibilitySpace::from(Dice(r as Int)).multiply(l as BigInt),
+                        ))
                     }
-                    _ => Err(EvalError::InvalidArgForDice),
                 }
-            }
+                _ => Err(EvalError::InvalidArgForDice),
+            },
         }
     }
-}
 
-impl Div for Value {
-    type Output = Result<EvalValue, EvalError>;
-
-    fn div(self, rhs: Self) -> Self::Output {
+    pub fn divide(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
             (Value::Default, Value::Default) => Ok(EvalValue::Constant(1)),
             (Value::Default, Value::Constant(c)) => {
-                if c == 0 {
+                if *c == 0 {
                     Err(EvalError::DivideByZero)
                 } else {
                     Ok(EvalValue::Constant(1 / c))
                 }
             }
-            (Value::Default, Value::Operator(o)) => match o.as_ref().clone().eval()? {
-                EvalValue::Constant(c) => {
-                    if c == 0 {
-                        Ok(EvalValue::Constant(1 / c))
-                    } else {
-                        Err(EvalError::DivideByZero)
-                    }
-                }
-                EvalValue::PreDice(d) => {
-                    let d: Distribution = d.into();
-                    if d.0.iter().any(|(x, _)| *x == 0) {
-                        Err(EvalError::DivideByZero)
-                    } else {
-                        Ok(EvalValue::PostDice(d.mutate(|x| 1 / x)))
-                    }
-                }
-                EvalValue::PostDice(d) => {
-                    if d.0.iter().any(|(x, _)| *x == 0) {
-                        Err(EvalError::DivideByZero)
-                    } else {
-                        Ok(EvalValue::PostDice(d.mutate(|x| 1 / x)))
-                    }
-                }
-            },
-            (Value::Constant(c), Value::Default) => Ok(EvalValue::Constant(c)),
+            (Value::Constant(c), Value::Default) => Ok(EvalValue::Constant(*c)),
             (Value::Constant(l), Value::Constant(r)) => {
-                if r == 0 {
+                if *r == 0 {
                     Err(EvalError::DivideByZero)
                 } else {
                     Ok(EvalValue::Constant(l / r))
                 }
             }
-            (Value::Constant(c), Value::Operator(o)) => match o.as_ref().clone().eval()? {
-                EvalValue::Constant(x) => {
-                    if x == 0 {
+            (Value::Default, o) => match o.eval_with(env)? {
+                EvalValue::Constant(c) => {
+                    if c == 0 {
                         Err(EvalError::DivideByZero)
                     } else {
-                        Ok(EvalValue::Constant(c / x))
+                        Ok(EvalValue::Constant(1 / c))
                     }
                 }
                 EvalValue::PreDice(d) => {
                     let d: Distribution = d.into();
-
                     if d.0.iter().any(|(x, _)| *x == 0) {
                         Err(EvalError::DivideByZero)
                     } else {
-                        Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) / x)))
+                        Ok(EvalValue::PostDice(d.mutate(|x| 1 / x)))
                     }
                 }
                 EvalValue::PostDice(d) => {
                     if d.0.iter().any(|(x, _)| *x == 0) {
                         Err(EvalError::DivideByZero)
                     } else {
-                        Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) / x)))
+                        Ok(EvalValue::PostDice(d.mutate(|x| 1 / x)))
                     }
                 }
             },
-            (Value::Operator(o), Value::Default) => o.as_ref().clone().eval(),
-            (Value::Operator(o), Value::Constant(c)) => {
-                if c == 0 {
-                    Err(EvalError::DivideByZero)
-                } else {
-                    Ok(match o.as_ref().clone().eval()? {
-                        EvalValue::Constant(x) => EvalValue::Constant(x / c),
-                        EvalValue::PreDice(d) => {
-                            EvalValue::PostDice(Distribution::from(d).mutate(|x| x / (c as Int)))
-                        }
-                        EvalValue::PostDice(d) => EvalValue::PostDice(d.mutate(|x| x / (c as Int))),
-                    })
-                }
-            }
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::Constant(l), EvalValue::Constant(r)) => {
-                        if r == 0 {
+            (Value::Constant(c), o) => {
+                let c = *c;
+                match o.eval_with(env)? {
+                    EvalValue::Constant(x) => {
+                        if x == 0 {
                             Err(EvalError::DivideByZero)
                         } else {
-                            Ok(EvalValue::Constant(l / r))
+                            Ok(EvalValue::Constant(c / x))
                         }
                     }
-                    (EvalValue::Constant(c), EvalValue::PreDice(d)) => {
+                    EvalValue::PreDice(d) => {
                         let d: Distribution = d.into();
+
                         if d.0.iter().any(|(x, _)| *x == 0) {
                             Err(EvalError::DivideByZero)
                         } else {
                             Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) / x)))
                         }
                     }
-                    (EvalValue::Constant(c), EvalValue::PostDice(d)) => {
+                    EvalValue::PostDice(d) => {
                         if d.0.iter().any(|(x, _)| *x == 0) {
                             Err(EvalError::DivideByZero)
                         } else {
                             Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) / x)))
                         }
                     }
-                    (EvalValue::PreDice(d), EvalValue::Constant(c)) => {
-                        if c == 0 {
-                            Err(EvalError::DivideByZero)
-                        } else {
-                            Ok(EvalValue::PostDice(
-                                Distribution::from(d).mutate(|x| x / (c as Int)),
-                            ))
+                }
+            }
+            (o, Value::Default) => o.eval_with(env),
+            (o, Value::Constant(c)) => {
+                let c = *c;
+                if c == 0 {
+                    Err(EvalError::DivideByZero)
+                } else {
+                    Ok(match o.eval_with(env)? {
+                        EvalValue::Constant(x) => EvalValue::Constant(x / c),
+                        EvalValue::PreDice(d) => {
+                            EvalValue::PostDice(Distribution::from(d).mutate(|x| x / (c as Int)))
                         }
+                        EvalValue::PostDice(d) => EvalValue::PostDice(d.mutate(|x| x / (c as Int))),
+                    })
+                }
+            }
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => {
+                    if r == 0 {
+                        Err(EvalError::DivideByZero)
+                    } else {
+                        Ok(EvalValue::Constant(l / r))
                     }
-                    (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
-                        if c == 0 {
-                            Err(EvalError::DivideByZero)
-                        } else {
-                            Ok(EvalValue::PostDice(d.mutate(|x| x / (c as Int))))
-                        }
+                }
+                (EvalValue::Constant(c), EvalValue::PreDice(d)) => {
+                    let d: Distribution = d.into();
+                    if d.0.iter().any(|(x, _)| *x == 0) {
+                        Err(EvalError::DivideByZero)
+                    } else {
+                        Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) / x)))
                     }
-                    _ => Err(EvalError::DivideDiceWithDice),
                 }
-            }
+                (EvalValue::Constant(c), EvalValue::PostDice(d)) => {
+                    if d.0.iter().any(|(x, _)| *x == 0) {
+                        Err(EvalError::DivideByZero)
+                    } else {
+                        Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) / x)))
+                    }
+                }
+                (EvalValue::PreDice(d), EvalValue::Constant(c)) => {
+                    if c == 0 {
+                        Err(EvalError::DivideByZero)
+                    } else {
+                        Ok(EvalValue::PostDice(
+                            Distribution::from(d).mutate(|x| x / (c as Int)),
+                        ))
+                    }
+                }
+                (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
+                    if c == 0 {
+                        Err(EvalError::DivideByZero)
+                    } else {
+                        Ok(EvalValue::PostDice(d.mutate(|x| x / (c as Int))))
+                    }
+                }
+                _ => Err(EvalError::DivideDiceWithDice),
+            },
         }
     }
-}
-
-impl Mul for Value {
-    type Output = Result<EvalValue, EvalError>;
 
-    fn mul(self, rhs: Self) -> Self::Output {
+    pub fn multiply(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
-            (Value::Default, Value::Default) | (Value::Operator(_), Value::Default) => {
-                Ok(EvalValue::Constant(1))
-            }
+            (Value::Default, Value::Default) => Ok(EvalValue::Constant(1)),
             (Value::Default, Value::Constant(c)) | (Value::Constant(c), Value::Default) => {
-                Ok(EvalValue::Constant(c))
+                Ok(EvalValue::Constant(*c))
             }
-            (Value::Default, Value::Operator(o)) => o.as_ref().clone().eval(),
+            (Value::Default, o) | (o, Value::Default) => o.eval_with(env),
             (Value::Constant(l), Value::Constant(r)) => Ok(EvalValue::Constant(l * r)),
-            (Value::Constant(c), Value::Operator(o)) | (Value::Operator(o), Value::Constant(c)) => {
-                match o.as_ref().clone().eval()? {
+            (Value::Constant(c), o) | (o, Value::Constant(c)) => {
+                let c = *c;
+                match o.eval_with(env)? {
                     EvalValue::Constant(x) => Ok(EvalValue::Constant(c * x)),
                     EvalValue::PreDice(d) => Ok(EvalValue::PostDice(
                         Distribution::from(d).mutate(|x| x * (c as Int)),
@@ -448,107 +1376,94 @@ impl Mul for Value {
                     EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| x * (c as Int)))),
                 }
             }
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::Constant(l), EvalValue::Constant(r)) => {
-                        Ok(EvalValue::Constant(l * r))
-                    }
-                    (EvalValue::Constant(c), EvalValue::PreDice(d))
-                    | (EvalValue::PreDice(d), EvalValue::Constant(c)) => Ok(EvalValue::PostDice(
-                        Distribution::from(d).mutate(|x| x * (c as Int)),
-                    )),
-                    (EvalValue::Constant(c), EvalValue::PostDice(d))
-                    | (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
-                        Ok(EvalValue::PostDice(d.mutate(|x| x * (c as Int))))
-                    }
-                    _ => Err(EvalError::MultiplyDiceWithDice),
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => Ok(EvalValue::Constant(l * r)),
+                (EvalValue::Constant(c), EvalValue::PreDice(d))
+                | (EvalValue::PreDice(d), EvalValue::Constant(c)) => Ok(EvalValue::PostDice(
+                    Distribution::from(d).mutate(|x| x * (c as Int)),
+                )),
+                (EvalValue::Constant(c), EvalValue::PostDice(d))
+                | (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
+                    Ok(EvalValue::PostDice(d.mutate(|x| x * (c as Int))))
                 }
-            }
+                _ => Err(EvalError::MultiplyDiceWithDice),
+            },
         }
     }
-}
 
-impl Sub for Value {
-    type Output = Result<EvalValue, EvalError>;
-
-    fn sub(self, rhs: Self) -> Self::Output {
+    pub fn minus(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
             (Value::Default, Value::Default) => Ok(EvalValue::Constant(0)),
             (Value::Default, Value::Constant(c)) => Ok(EvalValue::Constant(-c)),
-            (Value::Default, Value::Operator(c)) => match c.as_ref().clone().eval()? {
+            (Value::Constant(c), Value::Default) => Ok(EvalValue::Constant(*c)),
+            (Value::Constant(l), Value::Constant(r)) => Ok(EvalValue::Constant(l - r)),
+            (Value::Default, o) => match o.eval_with(env)? {
                 EvalValue::Constant(c) => Ok(EvalValue::Constant(-c)),
                 EvalValue::PreDice(d) => {
                     Ok(EvalValue::PostDice(Distribution::from(d).mutate(|x| -x)))
                 }
                 EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| -x))),
             },
-            (Value::Constant(c), Value::Default) => Ok(EvalValue::Constant(c)),
-            (Value::Constant(l), Value::Constant(r)) => Ok(EvalValue::Constant(l - r)),
-            (Value::Constant(c), Value::Operator(o)) => match o.as_ref().clone().eval()? {
-                EvalValue::Constant(x) => Ok(EvalValue::Constant(c - x)),
-                EvalValue::PreDice(d) => Ok(EvalValue::PostDice(
-                    Distribution::from(d).mutate(|x| (c as Int) - x),
-                )),
-                EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) - x))),
-            },
-            (Value::Operator(o), Value::Default) => o.as_ref().clone().eval(),
-            (Value::Operator(o), Value::Constant(c)) => match o.as_ref().clone().eval()? {
-                EvalValue::Constant(x) => Ok(EvalValue::Constant(x - c)),
-                EvalValue::PreDice(d) => Ok(EvalValue::PostDice(
-                    Distribution::from(d).mutate(|x| x - (c as Int)),
-                )),
-                EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| x - (c as Int)))),
-            },
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::Constant(l), EvalValue::Constant(r)) => {
-                        Ok(EvalValue::Constant(l - r))
-                    }
-                    (EvalValue::Constant(c), EvalValue::PreDice(d)) => Ok(EvalValue::PostDice(
+            (Value::Constant(c), o) => {
+                let c = *c;
+                match o.eval_with(env)? {
+                    EvalValue::Constant(x) => Ok(EvalValue::Constant(c - x)),
+                    EvalValue::PreDice(d) => Ok(EvalValue::PostDice(
                         Distribution::from(d).mutate(|x| (c as Int) - x),
                     )),
-                    (EvalValue::Constant(c), EvalValue::PostDice(d)) => {
-                        Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) - x)))
-                    }
-                    (EvalValue::PreDice(d), EvalValue::Constant(c)) => Ok(EvalValue::PostDice(
+                    EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) - x))),
+                }
+            }
+            (o, Value::Default) => o.eval_with(env),
+            (o, Value::Constant(c)) => {
+                let c = *c;
+                match o.eval_with(env)? {
+                    EvalValue::Constant(x) => Ok(EvalValue::Constant(x - c)),
+                    EvalValue::PreDice(d) => Ok(EvalValue::PostDice(
                         Distribution::from(d).mutate(|x| x - (c as Int)),
                     )),
-                    (EvalValue::PreDice(l), EvalValue::PreDice(r)) => Ok(EvalValue::PostDice(
-                        Distribution::from(l) - Distribution::from(r),
-                    )),
-                    (EvalValue::PreDice(l), EvalValue::PostDice(r)) => {
-                        Ok(EvalValue::PostDice(Distribution::from(l) - r))
-                    }
-                    (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
-                        Ok(EvalValue::PostDice(d.mutate(|x| x - (c as Int))))
-                    }
-                    (EvalValue::PostDice(l), EvalValue::PreDice(r)) => {
-                        Ok(EvalValue::PostDice(l - Distribution::from(r)))
-                    }
-                    (EvalValue::PostDice(l), EvalValue::PostDice(r)) => {
-                        Ok(EvalValue::PostDice(l - r))
-                    }
+                    EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| x - (c as Int)))),
                 }
             }
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => Ok(EvalValue::Constant(l - r)),
+                (EvalValue::Constant(c), EvalValue::PreDice(d)) => Ok(EvalValue::PostDice(
+                    Distribution::from(d).mutate(|x| (c as Int) - x),
+                )),
+                (EvalValue::Constant(c), EvalValue::PostDice(d)) => {
+                    Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) - x)))
+                }
+                (EvalValue::PreDice(d), EvalValue::Constant(c)) => Ok(EvalValue::PostDice(
+                    Distribution::from(d).mutate(|x| x - (c as Int)),
+                )),
+                (EvalValue::PreDice(l), EvalValue::PreDice(r)) => Ok(EvalValue::PostDice(
+                    Distribution::from(l) - Distribution::from(r),
+                )),
+                (EvalValue::PreDice(l), EvalValue::PostDice(r)) => {
+                    Ok(EvalValue::PostDice(Distribution::from(l) - r))
+                }
+                (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
+                    Ok(EvalValue::PostDice(d.mutate(|x| x - (c as Int))))
+                }
+                (EvalValue::PostDice(l), EvalValue::PreDice(r)) => {
+                    Ok(EvalValue::PostDice(l - Distribution::from(r)))
+                }
+                (EvalValue::PostDice(l), EvalValue::PostDice(r)) => Ok(EvalValue::PostDice(l - r)),
+            },
         }
     }
-}
-
-impl Add for Value {
-    type Output = Result<EvalValue, EvalError>;
 
-    fn add(self, rhs: Self) -> Self::Output {
+    pub fn plus(&self, rhs: &Self, env: &Env) -> Result<EvalValue, EvalError> {
         match (self, rhs) {
             (Value::Default, Value::Default) => Ok(EvalValue::Constant(0)),
             (Value::Default, Value::Constant(x)) | (Value::Constant(x), Value::Default) => {
-                Ok(EvalValue::Constant(x))
-            }
-            (Value::Default, Value::Operator(x)) | (Value::Operator(x), Value::Default) => {
-                x.as_ref().clone().eval()
+                Ok(EvalValue::Constant(*x))
             }
+            (Value::Default, x) | (x, Value::Default) => x.eval_with(env),
             (Value::Constant(l), Value::Constant(r)) => Ok(EvalValue::Constant(l + r)),
-            (Value::Constant(c), Value::Operator(o)) | (Value::Operator(o), Value::Constant(c)) => {
-                match o.as_ref().clone().eval()? {
+            (Value::Constant(c), o) | (o, Value::Constant(c)) => {
+                let c = *c;
+                match o.eval_with(env)? {
                     EvalValue::Constant(x) => Ok(EvalValue::Constant(x + c)),
                     EvalValue::PreDice(d) => Ok(EvalValue::PostDice(
                         Distribution::from(d).mutate(|x| x + (c as Int)),
@@ -556,31 +1471,25 @@ impl Add for Value {
                     EvalValue::PostDice(d) => Ok(EvalValue::PostDice(d.mutate(|x| x + (c as Int)))),
                 }
             }
-            (Value::Operator(l), Value::Operator(r)) => {
-                match (l.as_ref().clone().eval()?, r.as_ref().clone().eval()?) {
-                    (EvalValue::Constant(l), EvalValue::Constant(r)) => {
-                        Ok(EvalValue::Constant(l + r))
-                    }
-                    (EvalValue::Constant(c), EvalValue::PreDice(d))
-                    | (EvalValue::PreDice(d), EvalValue::Constant(c)) => Ok(EvalValue::PostDice(
-                        Distribution::from(d).mutate(|x| (c as Int) + x),
-                    )),
-                    (EvalValue::Constant(c), EvalValue::PostDice(d))
-                    | (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
-                        Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) + x)))
-                    }
-                    (EvalValue::PreDice(pre), EvalValue::PostDice(post))
-                    | (EvalValue::PostDice(post), EvalValue::PreDice(pre)) => {
-                        Ok(EvalValue::PostDice(Distribution::from(pre) + post))
-                    }
-                    (EvalValue::PreDice(l), EvalValue::PreDice(r)) => Ok(EvalValue::PostDice(
-                        Distribution::from(l) + Distribution::from(r),
-                    )),
-                    (EvalValue::PostDice(l), EvalValue::PostDice(r)) => {
-                        Ok(EvalValue::PostDice(l + r))
-                    }
+            (l, r) => match (l.eval_with(env)?, r.eval_with(env)?) {
+                (EvalValue::Constant(l), EvalValue::Constant(r)) => Ok(EvalValue::Constant(l + r)),
+                (EvalValue::Constant(c), EvalValue::PreDice(d))
+                | (EvalValue::PreDice(d), EvalValue::Constant(c)) => Ok(EvalValue::PostDice(
+                    Distribution::from(d).mutate(|x| (c as Int) + x),
+                )),
+                (EvalValue::Constant(c), EvalValue::PostDice(d))
+                | (EvalValue::PostDice(d), EvalValue::Constant(c)) => {
+                    Ok(EvalValue::PostDice(d.mutate(|x| (c as Int) + x)))
                 }
-            }
+                (EvalValue::PreDice(pre), EvalValue::PostDice(post))
+                | (EvalValue::PostDice(post), EvalValue::PreDice(pre)) => {
+                    Ok(EvalValue::PostDice(Distribution::from(pre) + post))
+                }
+                (EvalValue::PreDice(l), EvalValue::PreDice(r)) => Ok(EvalValue::PostDice(
+                    Distribution::from(l) + Distribution::from(r),
+                )),
+                (EvalValue::PostDice(l), EvalValue::PostDice(r)) => Ok(EvalValue::PostDice(l + r)),
+            },
         }
     }
 }
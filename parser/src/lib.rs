@@ -1,2 +1,4 @@
 pub mod ast;
+pub mod compiled;
 pub mod eval;
+pub mod optimize;
@@ -0,0 +1,383 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    ast::{Function, Operator, Value},
+    eval::{Env, EvalValue},
+};
+
+#[cfg(test)]
+// the tests go at the top like in the other files
+#[allow(clippy::items_after_test_module)]
+mod tests {
+    use model::distribution::Distribution;
+    use shoulda::Shoulda;
+
+    use crate::ast::Value;
+
+    fn optimized(s: &str) -> String {
+        s.parse::<Value>().unwrap().optimize().to_string()
+    }
+
+    #[test]
+    fn folds_constants() {
+        optimized("2d6+3+4-1").should().eq("2d6 + 6".to_string());
+        optimized("(1+1)d(3*2)").should().eq("2d6".to_string());
+        optimized("10 - 2 * 3").should().eq("4".to_string());
+        optimized("4d6kh(5-2)").should().eq("4d6kh3".to_string());
+        optimized("max(1, 2 + 3)").should().eq("5".to_string());
+        optimized("floor(-7 / (1 + 1))")
+            .should()
+            .eq("-4".to_string());
+        optimized("if 1 + 1 == 2 then d6 + d6 else 1 / 0")
+            .should()
+            .eq("2d6".to_string());
+        optimized("ceil(2d6 / (1 + 1))")
+            .should()
+            .eq("ceil(2d6 / 2)".to_string());
+    }
+
+    #[test]
+    fn merges_dice_pools() {
+        optimized("d6+d6").should().eq("2d6".to_string());
+        optimized("3 + 2d8 - d4 + d8 - 1 - d4")
+            .should()
+            .eq("3d8 - 2d4 + 2".to_string());
+        optimized("5 - d6 - d6").should().eq("5 - 2d6".to_string());
+        optimized("-d6 - d6").should().eq("-2d6".to_string());
+        optimized("d6 - d6").should().eq("d6 - d6".to_string());
+    }
+
+    #[test]
+    fn keeps_what_would_change_the_result() {
+        optimized("(--d6)kh1").should().eq("(--d6)kh1".to_string());
+        optimized("(d6+d6)kh1")
+            .should()
+            .eq("(d6 + d6)kh1".to_string());
+        optimized("10 / 0").should().eq("10 / 0".to_string());
+        optimized("0d6 + 1 + 1").should().eq("0d6 + 2".to_string());
+        optimized("x + 1 + 1").should().eq("x + 2".to_string());
+    }
+
+    #[test]
+    fn same_distribution() {
+        for s in [
+            "2d6+3+4-1",
+            "d6+d6-d4+2*3",
+            "-d6 - d6 - 1",
+            "-(1d4 + 1) * -2",
+            "(2d6 + 1) * 2 - d4 + 3",
+            "(d20 + 5) / (2 + 1)",
+            "4d6kh3 + 4d6kh3 + 2",
+            "3d6 + (1 + 2)d6",
+            "*4 + d + d + / 2",
+            "10 - (3 - d6 - 2)",
+            "max(d6 + d6, 3 + 4) + min(1 + 1, d4)",
+            "let x = d6 + d6; roll y = 1 + d4 + 1; x + y + y",
+            "if d6 + d6 >= 3 + 4 then d4 + d4 else 1 + 1",
+            "(if 2 > 1 then d6 + d6 else d6)kh1",
+            "(d6 + d6 | d6 + d6 >= 3 + 4) + 1 + 1",
+            "mix(1/3: d4 + d4, 2: 1 + 2) + 1 + 1",
+            "d20 + 1 + 2 vs d20 + 5",
+            "10d10[>=4 + 4] + 1 + 1",
+            "5d10[8..10] - 2d4[1] * 2",
+            "10d10[>=7]f(1 + 0) + 1 + 1",
+        ] {
+            let value: Value = s.parse().unwrap();
+            let optimized = value.optimize().eval().map(Distribution::from);
+            optimized.should().eq(value.eval().map(Distribution::from));
+        }
+    }
+}
+
+impl Value {
+    /// an expression with the same distribution that is cheaper to evaluate, constants are
+    /// folded, dice pools with the same sides are merged and every additive constant is moved into
+    /// a single shift at the end
+    pub fn optimize(&self) -> Value {
+        optimize(self, false)
+    }
+}
+
+fn operator(op: Operator) -> Value {
+    Value::Operator(Rc::new(op))
+}
+
+/// `pool` is set where the value is used as a dice pool, which a sum must never be turned into
+fn optimize(value: &Value, pool: bool) -> Value {
+    let Value::Operator(op) = value else {
+        return value.clone();
+    };
+    match op.as_ref() {
+        Operator::Plus(_, _) | Operator::Minus(_, _) => sum(value, pool),
+        Operator::Multiply(l, r) => {
+            let l = optimize(l, pool && matches!(r, Value::Default));
+            let r = optimize(r, pool && matches!(l, Value::Default));
+            let folded = match (&l, &r) {
+                (Value::Default, Value::Default) => Some(1),
+                (Value::Default, Value::Constant(c)) | (Value::Constant(c), Value::Default) => {
+                    Some(*c)
+                }
+                (Value::Constant(l), Value::Constant(r)) => l.checked_mul(*r),
+                _ => None,
+            };
+            folded
+                .map(Value::Constant)
+                .unwrap_or_else(|| operator(Operator::Multiply(l, r)))
+        }
+        Operator::Divide(l, r) => {
+            let l = optimize(l, pool && matches!(r, Value::Default));
+            let r = optimize(r, false);
+            let folded = match (&l, &r) {
+                (Value::Default, Value::Default) => Some(1),
+                (Value::Default, Value::Constant(c)) => 1i32.checked_div(*c),
+                (Value::Constant(c), Value::Default) => Some(*c),
+                (Value::Constant(l), Value::Constant(r)) => l.checked_div(*r),
+                _ => None,
+            };
+            folded
+                .map(Value::Constant)
+                .unwrap_or_else(|| operator(Operator::Divide(l, r)))
+        }
+        Operator::KeepHighest(l, r) => {
+            operator(Operator::KeepHighest(optimize(l, true), optimize(r, false)))
+        }
+        Operator::CountSuccesses(l, r) => operator(Operator::CountSuccesses(
+            optimize(l, true),
+            optimize(r, false),
+        )),
+        Operator::Successes(comparison, l, r) => operator(Operator::Successes(
+            *comparison,
+            optimize(l, true),
+            optimize(r, false),
+        )),
+        Operator::Faces(pool, faces) => {
+            operator(Operator::Faces(optimize(pool, true), faces.clone()))
+        }
+        Operator::Failures(successes, comparison, threshold) => operator(Operator::Failures(
+            optimize(successes, true),
+            *comparison,
+            optimize(threshold, false),
+        )),
+        Operator::Dice(l, r) => operator(Operator::Dice(optimize(l, false), optimize(r, false))),
+        Operator::Negate(_) => sum(value, pool),
+        Operator::Call(function, args) => {
+            let rounds = matches!(function, Function::Floor | Function::Ceil);
+            let args = args
+                .iter()
+                .map(|x| match x {
+                    // `floor` and `ceil` round the division themselves, so it can't be folded
+                    Value::Operator(op) if rounds => match op.as_ref() {
+                        Operator::Divide(l, r) => {
+                            operator(Operator::Divide(optimize(l, false), optimize(r, false)))
+                        }
+                        _ => optimize(x, false),
+                    },
+                    _ => optimize(x, false),
+                })
+                .collect::<Vec<_>>();
+            let constant = args.iter().all(|x| match x {
+                Value::Constant(_) => true,
+                Value::Operator(op) => matches!(
+                    op.as_ref(),
+                    Operator::Divide(Value::Constant(_), Value::Constant(_))
+                ),
+                _ => false,
+            });
+            let call = operator(Operator::Call(*function, args));
+            if constant {
+                if let Ok(EvalValue::Constant(c)) = call.eval_with(&Env::new()) {
+                    return Value::Constant(c);
+                }
+            }
+            call
+        }
+        // a bound value could be kept from, so it is treated like a pool
+        Operator::Let(name, value, body) => operator(Operator::Let(
+            name.clone(),
+            optimize(value, true),
+            optimize(body, pool),
+        )),
+        Operator::Roll(name, value, body) => operator(Operator::Roll(
+            name.clone(),
+            optimize(value, false),
+            optimize(body, pool),
+        )),
+        Operator::Compare(comparison, l, r) => match (optimize(l, false), optimize(r, false)) {
+            (Value::Constant(l), Value::Constant(r)) => {
+                Value::Constant(comparison.holds(l, r) as i32)
+            }
+            (Value::Default, Value::Default) => Value::Constant(comparison.holds(0, 0) as i32),
+            (l, r) => operator(Operator::Compare(*comparison, l, r)),
+        },
+        Operator::Versus(l, r) => match (optimize(l, false), optimize(r, false)) {
+            (Value::Constant(l), Value::Constant(r)) => Value::Constant((l > r) as i32),
+            (l, r) => operator(Operator::Versus(l, r)),
+        },
+        Operator::Mix(parts) => operator(Operator::Mix(
+            parts
+                .iter()
+                .map(|(weight, value)| (*weight, optimize(value, false)))
+                .collect(),
+        )),
+        Operator::Given(x, condition) => operator(Operator::Given(
+            optimize(x, false),
+            optimize(condition, false),
+        )),
+        Operator::If(condition, then, otherwise) => match optimize(condition, false) {
+            Value::Constant(0) | Value::Default => optimize(otherwise, pool),
+            Value::Constant(_) => optimize(then, pool),
+            condition => operator(Operator::If(
+                condition,
+                optimize(then, false),
+                optimize(otherwise, false),
+            )),
+        },
+    }
+}
+
+/// a chain of additions and subtractions as its terms, with whether each one is subtracted
+fn terms(value: &Value, negative: bool, out: &mut Vec<(bool, Value)>) {
+    match value {
+        Value::Default => {}
+        Value::Operator(op) => match op.as_ref() {
+            Operator::Plus(l, r) => {
+                terms(l, negative, out);
+                terms(r, negative, out);
+            }
+            Operator::Minus(l, r) => {
+                terms(l, negative, out);
+                terms(r, !negative, out);
+            }
+            Operator::Negate(x) => terms(x, !negative, out),
+            _ => out.push((negative, optimize(value, false))),
+        },
+        _ => out.push((negative, value.clone())),
+    }
+}
+
+/// the amount and sides of a pool of dice, as long as it is valid
+fn dice_pool(value: &Value) -> Option<(i32, i32)> {
+    let Value::Operator(op) = value else {
+        return None;
+    };
+    let Operator::Dice(l, r) = op.as_ref() else {
+        return None;
+    };
+    let amount = match l {
+        Value::Default => 1,
+        Value::Constant(c) => *c,
+        _ => return None,
+    };
+    let sides = match r {
+        Value::Default => 6,
+        Value::Constant(c) => *c,
+        _ => return None,
+    };
+    (amount >= 1 && sides >= 1).then_some((amount, sides))
+}
+
+fn sum(value: &Value, pool: bool) -> Value {
+    let mut all = Vec::new();
+    terms(value, false, &mut all);
+
+    let mut constant = Some(0i32);
+    let mut rest: Vec<(bool, Value)> = Vec::new();
+    // the index in `rest` of the merged pool for each sign and number of sides
+    let mut pools: HashMap<(bool, i32), (usize, i32)> = HashMap::new();
+    for (negative, term) in all {
+        if let Value::Constant(c) = term {
+            constant = constant.and_then(|x| {
+                if negative {
+                    x.checked_sub(c)
+                } else {
+                    x.checked_add(c)
+                }
+            });
+        } else if let Some((amount, sides)) = dice_pool(&term) {
+            match pools.get_mut(&(negative, sides)) {
+                Some((_, total)) => match total.checked_add(amount) {
+                    Some(x) => *total = x,
+                    None => rest.push((negative, term)),
+                },
+                None => {
+                    pools.insert((negative, sides), (rest.len(), amount));
+                    rest.push((negative, term));
+                }
+            }
+        } else {
+            rest.push((negative, term));
+        }
+    }
+    let Some(constant) = constant else {
+        return keep(value, pool);
+    };
+    for ((negative, sides), (i, amount)) in pools {
+        if dice_pool(&rest[i].1) == Some((amount, sides)) {
+            continue;
+        }
+        rest[i] = (
+            negative,
+            operator(Operator::Dice(
+                Value::Constant(amount),
+                Value::Constant(sides),
+            )),
+        );
+    }
+    // subtracted terms last so the chain can start with one that is added
+    rest.sort_by_key(|(negative, _)| *negative);
+
+    let mut rest = rest.into_iter();
+    let (mut acc, constant) = match rest.next() {
+        None => return Value::Constant(constant),
+        Some((false, first)) => (first, constant),
+        Some((true, first)) if constant != 0 => (
+            operator(Operator::Minus(Value::Constant(constant), first)),
+            0,
+        ),
+        Some((true, first)) => (operator(Operator::Negate(first)), 0),
+    };
+    for (negative, term) in rest {
+        acc = operator(if negative {
+            Operator::Minus(acc, term)
+        } else {
+            Operator::Plus(acc, term)
+        });
+    }
+    if constant != 0 {
+        acc = operator(match constant.checked_neg() {
+            Some(x) if constant < 0 => Operator::Minus(acc, Value::Constant(x)),
+            _ => Operator::Plus(acc, Value::Constant(constant)),
+        });
+    }
+    // a pool can be kept from and a sum can't, so a sum that is used as a pool has to stay one
+    let still_a_sum = match &acc {
+        Value::Operator(op) => matches!(
+            op.as_ref(),
+            Operator::Plus(_, _) | Operator::Minus(_, _) | Operator::Negate(_)
+        ),
+        _ => false,
+    };
+    if pool && !still_a_sum {
+        return keep(value, pool);
+    }
+    acc
+}
+
+/// `value` with its structure unchanged, only its operands are optimized
+fn keep(value: &Value, pool: bool) -> Value {
+    let Value::Operator(op) = value else {
+        return value.clone();
+    };
+    match op.as_ref() {
+        Operator::Plus(l, r) => operator(Operator::Plus(
+            optimize(l, pool && matches!(r, Value::Default)),
+            optimize(r, pool && matches!(l, Value::Default)),
+        )),
+        Operator::Minus(l, r) => operator(Operator::Minus(
+            optimize(l, pool && matches!(r, Value::Default)),
+            optimize(r, false),
+        )),
+        Operator::Negate(x) => operator(Operator::Negate(optimize(x, false))),
+        _ => optimize(value, pool),
+    }
+}
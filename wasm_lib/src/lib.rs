@@ -1,8 +1,11 @@
 use std::{error::Error, fmt::Display, str::FromStr};
 
 use js_sys::Map;
-use model::distribution::Distribution;
-use parser::{ast::ValueParseError, eval::EvalError};
+use model::{distribution::Distribution, rng::Rng, BigInt, Int};
+use parser::{
+    ast::ValueParseError,
+    eval::{EvalError, EvalValue},
+};
 use wasm_bindgen::prelude::*;
 
 #[derive(Debug)]
@@ -22,19 +25,265 @@ impl Display for DistError {
 
 impl Error for DistError {}
 
+fn eval(s: &str) -> Result<Distribution, DistError> {
+    let ast = parser::ast::Value::from_str(s).map_err(DistError::Parse)?;
+    Ok(ast.eval().map_err(DistError::Eval)?.into())
+}
+
+/// How counts are handed over to JavaScript
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CountFormat {
+    /// counts as `number`, which is only exact up to 2^53
+    #[default]
+    Number,
+    /// exact counts as `bigint`
+    BigInt,
+    /// exact counts as decimal strings
+    String,
+    /// the probability of each outcome as a `number` instead of its count
+    Probability,
+}
+
 #[wasm_bindgen]
-pub fn dice_eval(s: &str) -> Result<Map, String> {
-    let ast = parser::ast::Value::from_str(s)
-        .map_err(DistError::Parse)
-        .map_err(|x| x.to_string())?;
-    let dist: Distribution = ast
-        .eval()
-        .map_err(DistError::Eval)
-        .map_err(|x| x.to_string())?
-        .into();
+pub fn dice_eval(s: &str, format: Option<CountFormat>) -> Result<Map, String> {
+    let dist = eval(s).map_err(|x| x.to_string())?;
+    let total = dist.total() as f64;
+    let format = format.unwrap_or_default();
     let map = Map::new();
     for (k, v) in dist.0 {
-        map.set(&JsValue::from_f64(k as f64), &JsValue::from_f64(v as f64));
+        let v = match format {
+            CountFormat::Number => JsValue::from_f64(v as f64),
+            CountFormat::BigInt => JsValue::from(v),
+            CountFormat::String => JsValue::from(v.to_string()),
+            CountFormat::Probability => JsValue::from_f64(v as f64 / total),
+        };
+        map.set(&JsValue::from_f64(k as f64), &v);
     }
     Ok(map)
 }
+
+/// Evaluates `s` into a [`DiceResult`], or a [`DiceError`] describing what went wrong and where
+#[wasm_bindgen]
+pub fn evaluate(s: &str) -> Result<DiceResult, DiceError> {
+    eval(s)
+        .map(DiceResult::from)
+        .map_err(|err| DiceError::new(err, s))
+}
+
+/// A parsed expression that can be evaluated over and over, e.g. with different variables,
+/// without redoing the parts that didn't change
+#[wasm_bindgen]
+pub struct CompiledExpression {
+    source: String,
+    inner: parser::compiled::CompiledExpression,
+}
+
+#[wasm_bindgen]
+impl CompiledExpression {
+    #[wasm_bindgen(constructor)]
+    pub fn new(s: &str) -> Result<CompiledExpression, DiceError> {
+        let inner = s
+            .parse()
+            .map_err(|err| DiceError::new(DistError::Parse(err), s))?;
+        Ok(Self {
+            source: s.to_string(),
+            inner,
+        })
+    }
+
+    fn eval(&self) -> Result<Distribution, DiceError> {
+        self.inner
+            .distribution()
+            .map_err(|err| DiceError::new(DistError::Eval(err), &self.source))
+    }
+
+    pub fn distribution(&self) -> Result<DiceResult, DiceError> {
+        self.eval().map(DiceResult::from)
+    }
+
+    /// rolls the expression once, the same seed always gives the same result
+    pub fn roll(&self, seed: u64) -> Result<Int, DiceError> {
+        Ok(self.eval()?.roll(&mut Rng::new(seed)))
+    }
+
+    #[wasm_bindgen(js_name = probabilityAtLeast)]
+    pub fn probability_at_least(&self, n: Int) -> Result<f64, DiceError> {
+        Ok(self.eval()?.probability_at_least(n))
+    }
+
+    /// the optimized expression with normalized spacing and only the parentheses it needs,
+    /// equivalent expressions usually give the same string
+    #[wasm_bindgen(getter)]
+    pub fn canonical(&self) -> String {
+        self.inner.value().to_string()
+    }
+
+    /// a copy of this expression with `name` set to `value`
+    #[wasm_bindgen(js_name = withVariable)]
+    pub fn with_variable(&self, name: &str, value: Int) -> CompiledExpression {
+        Self {
+            source: self.source.clone(),
+            inner: self.inner.with_variable(name, EvalValue::Constant(value)),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct DiceResult {
+    outcomes: Vec<f64>,
+    counts: Vec<BigInt>,
+    probabilities: Vec<f64>,
+    cumulative: Vec<f64>,
+    total: BigInt,
+    mean: f64,
+    variance: f64,
+    standard_deviation: f64,
+}
+
+impl From<Distribution> for DiceResult {
+    fn from(dist: Distribution) -> Self {
+        let (outcomes, counts) = dist
+            .sorted()
+            .into_iter()
+            .map(|(outcome, count)| (outcome as f64, count))
+            .unzip();
+        Self {
+            outcomes,
+            counts,
+            probabilities: dist.probabilities().into_iter().map(|(_, p)| p).collect(),
+            cumulative: dist.cumulative().into_iter().map(|(_, p)| p).collect(),
+            total: dist.total(),
+            mean: dist.mean(),
+            variance: dist.variance(),
+            standard_deviation: dist.standard_deviation(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DiceResult {
+    /// every possible outcome, in ascending order
+    #[wasm_bindgen(getter)]
+    pub fn outcomes(&self) -> Vec<f64> {
+        self.outcomes.clone()
+    }
+
+    /// the number of ways to roll each outcome, in the same order as `outcomes`
+    #[wasm_bindgen(getter)]
+    pub fn counts(&self) -> Vec<js_sys::BigInt> {
+        self.counts
+            .iter()
+            .map(|x| js_sys::BigInt::from(*x))
+            .collect()
+    }
+
+    /// `counts` as decimal strings, for consumers that can't handle `bigint`
+    #[wasm_bindgen(getter, js_name = countStrings)]
+    pub fn count_strings(&self) -> Vec<String> {
+        self.counts.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.probabilities.clone()
+    }
+
+    /// the probability of rolling each outcome or anything below it
+    #[wasm_bindgen(getter)]
+    pub fn cumulative(&self) -> Vec<f64> {
+        self.cumulative.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> js_sys::BigInt {
+        js_sys::BigInt::from(self.total)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    #[wasm_bindgen(getter, js_name = standardDeviation)]
+    pub fn standard_deviation(&self) -> f64 {
+        self.standard_deviation
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn min(&self) -> Option<f64> {
+        self.outcomes.first().copied()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn max(&self) -> Option<f64> {
+        self.outcomes.last().copied()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub enum ErrorKind {
+    Parse,
+    Eval,
+}
+
+/// A byte range into the evaluated expression
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct DiceError {
+    kind: ErrorKind,
+    message: String,
+    span: Option<Span>,
+}
+
+impl DiceError {
+    fn new(err: DistError, source: &str) -> Self {
+        match err {
+            DistError::Eval(eval) => Self {
+                kind: ErrorKind::Eval,
+                message: eval.to_string(),
+                span: None,
+            },
+            DistError::Parse(parse) => Self {
+                kind: ErrorKind::Parse,
+                message: parse.to_string(),
+                span: parse.span(source).map(|x| Span {
+                    start: x.start,
+                    end: x.end,
+                }),
+            },
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DiceError {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// where in the expression the error happened, if it could be located
+    #[wasm_bindgen(getter)]
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+}